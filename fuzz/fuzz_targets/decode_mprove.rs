@@ -0,0 +1,32 @@
+#![no_main]
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Decodes arbitrary bytes as an MProve proof and verifies whatever decodes
+// against a fixed statement of the same size. Neither step may panic.
+use libfuzzer_sys::fuzz_target;
+use mprove_ristretto::proofs::backend::Ristretto;
+use mprove_ristretto::proofs::fixtures::Fixture;
+use mprove_ristretto::proofs::mprove::MProveProof;
+
+// larger sets only cost time
+const MAX_SET: usize = 16;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = MProveProof::<Ristretto>::from_bytes(data) {
+        assert_eq!(proof.to_bytes(), data);
+        let n = proof.C_prime_vec().len();
+        if (1..=MAX_SET).contains(&n) {
+            let fixture = Fixture::<Ristretto>::generate(n, 0, 0).unwrap();
+            assert!(proof.verify(&fixture.stmt).is_err());
+        }
+    }
+});
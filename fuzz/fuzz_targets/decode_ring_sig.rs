@@ -0,0 +1,48 @@
+#![no_main]
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Decodes arbitrary bytes as a RingSig and as an LSAGSig, and verifies
+// whatever decodes against a fixed ring of the same size. Neither step may
+// panic, and a decoded signature can only verify by forging one.
+use libfuzzer_sys::fuzz_target;
+use mprove_ristretto::proofs::backend::{Curve, Ristretto};
+use mprove_ristretto::proofs::mprove_sigs::{LSAGSig, RingSig};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+// larger rings only cost time
+const MAX_RING: usize = 32;
+
+fn ring(n: usize) -> (Vec<<Ristretto as Curve>::Point>, <Ristretto as Curve>::Point) {
+    let mut rng = ChaCha20Rng::seed_from_u64(n as u64);
+    let pk: Vec<_> = (0..n).map(|_| <Ristretto>::random_point(&mut rng)).collect();
+    (pk, <Ristretto>::random_point(&mut rng))
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(sig) = RingSig::<Ristretto>::from_bytes(data) {
+        assert_eq!(sig.to_bytes(), data);
+        let n = (data.len() - 4) / 32 - 1;
+        if n <= MAX_RING {
+            let (pk, message) = ring(n);
+            assert!(sig.ver_RingSig(message, &pk).is_err());
+        }
+    }
+    if let Ok(sig) = LSAGSig::<Ristretto>::from_bytes(data) {
+        assert_eq!(sig.to_bytes(), data);
+        let n = (data.len() - 4) / 32 - 2;
+        if n <= MAX_RING {
+            let (pk, message) = ring(n);
+            assert!(sig.ver_LSAG(message, &pk).is_err());
+        }
+    }
+});
@@ -0,0 +1,45 @@
+#![no_main]
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Mutates a valid MProve proof and checks that no mutation verifies. The
+// input is read as (offset u16 little endian, xor mask) triples applied to
+// the encoding of the proof.
+use libfuzzer_sys::fuzz_target;
+use mprove_ristretto::proofs::backend::Ristretto;
+use mprove_ristretto::proofs::fixtures::Fixture;
+use mprove_ristretto::proofs::mprove::{MProveProof, MProveStatement};
+
+use std::sync::OnceLock;
+
+fn valid() -> &'static (MProveStatement<Ristretto>, Vec<u8>) {
+    static VALID: OnceLock<(MProveStatement<Ristretto>, Vec<u8>)> = OnceLock::new();
+    VALID.get_or_init(|| {
+        let Fixture { stmt, witness } = Fixture::<Ristretto>::generate(4, 2, 0).unwrap();
+        let bytes = MProveProof::prove(&stmt, &witness).unwrap().to_bytes();
+        (stmt, bytes)
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (stmt, bytes) = valid();
+    let mut mutated = bytes.clone();
+    for chunk in data.chunks_exact(3) {
+        let offset = u16::from_le_bytes([chunk[0], chunk[1]]) as usize % mutated.len();
+        mutated[offset] ^= chunk[2];
+    }
+    if mutated == *bytes {
+        return;
+    }
+    if let Ok(proof) = MProveProof::<Ristretto>::from_bytes(&mutated) {
+        assert!(proof.verify(stmt).is_err());
+    }
+});
@@ -1,27 +1,244 @@
 extern crate structopt;
 extern crate mprove_ristretto;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate rand;
+extern crate hex;
+extern crate curve25519_dalek;
 
+use curve25519_dalek::scalar::Scalar;
+use structopt::clap;
 use structopt::StructOpt;
-use std::time::{Instant, Duration};
-use mprove_ristretto::proofs::mprove::MProve;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
+use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
+use mprove_ristretto::proofs::anon_set::OutputExport;
+use mprove_ristretto::proofs::backend::{Curve, MoneroEd25519, Ristretto, RistrettoBlake2b, RistrettoSha512};
+use mprove_ristretto::proofs::export::{decode_point, decode_scalar};
+use mprove_ristretto::proofs::fixtures::Fixture;
+use mprove_ristretto::proofs::generators::Generators;
+use mprove_ristretto::proofs::mprove::{MProveProof, MProveSize, MProveStatement};
+use mprove_ristretto::proofs::report::AuditReport;
+
+#[derive(Debug)]
+enum Format {
+  Text,
+  Json,
+}
+
+impl FromStr for Format {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Format, String> {
+    match s {
+      "text" => Ok(Format::Text),
+      "json" => Ok(Format::Json),
+      _ => Err(format!("unknown format `{}` (expected `text` or `json`)", s)),
+    }
+  }
+}
+
+#[derive(Debug)]
+enum CurveName {
+  Ristretto,
+  RistrettoSha512,
+  RistrettoBlake2b,
+  Monero,
+}
+
+impl FromStr for CurveName {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<CurveName, String> {
+    match s {
+      "ristretto" => Ok(CurveName::Ristretto),
+      "ristretto-sha512" => Ok(CurveName::RistrettoSha512),
+      "ristretto-blake2b" => Ok(CurveName::RistrettoBlake2b),
+      "monero" => Ok(CurveName::Monero),
+      _ => Err(format!(
+        "unknown curve `{}` (expected `ristretto`, `ristretto-sha512`, `ristretto-blake2b` or `monero`)",
+        s
+      )),
+    }
+  }
+}
+
+// number of iterations, which must be at least one to average over
+fn parse_num_iter(s: &str) -> Result<u32, String> {
+  match s.parse::<u32>() {
+    Ok(0) => Err("the number of iterations must be at least 1".to_string()),
+    Ok(n) => Ok(n),
+    Err(e) => Err(e.to_string()),
+  }
+}
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "mprove", about = "MProve proof generation simulator using Ristretto.")]
+#[structopt(name = "mprove", about = "MProve proof generation simulator using Ristretto or Monero's Ed25519.")]
 struct Opt {
+  #[structopt(subcommand)]
+  command: Option<Command>,
   //#[structopt(short = "a", long = "anonsize")]
-  anon_list_size: usize,
+  anon_list_size: Option<usize>,
   //#[structopt(short = "o", long = "ownsize")]
+  own_list_size: Option<usize>,
+  #[structopt(short = "n", long = "numiter", default_value = "1", parse(try_from_str = "parse_num_iter"))]
+  num_iter: u32,
+  /// Seed for the simulated anonymity set (random if not given)
+  #[structopt(long = "seed")]
+  seed: Option<u64>,
+  /// Curve backend: `ristretto` (Keccak-512), `ristretto-sha512`,
+  /// `ristretto-blake2b` or `monero`
+  #[structopt(long = "curve", default_value = "ristretto")]
+  curve: CurveName,
+  /// Output format: `text` or `json`
+  #[structopt(long = "format", default_value = "text")]
+  format: Format,
+  /// Write a flamegraph of the prove/verify spans to this file
+  /// (requires the `profiling` feature).
+  #[structopt(long = "profile", parse(from_os_str))]
+  profile: Option<PathBuf>,
+  /// Write the anonymity set (anon_set.json) and the last proof (proof.bin)
+  /// to this directory, for `mprove_bin verify`
+  #[structopt(long = "export", parse(from_os_str))]
+  export: Option<PathBuf>,
+  /// File holding the exchange's identity key, as hex, to claim the proofs
+  /// with
+  #[structopt(long = "identity-key", parse(from_os_str))]
+  identity_key: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+  /// Verifies an MProve proof and optionally issues a signed audit report
+  #[structopt(name = "verify")]
+  Verify(VerifyOpt),
+}
+
+#[derive(Debug, StructOpt)]
+struct VerifyOpt {
+  /// Anonymity set, as an output export (JSON or CSV) listing exactly the
+  /// outputs of the statement, in order
+  #[structopt(long = "anon-set", parse(from_os_str))]
+  anon_set: PathBuf,
+  /// Proof, in its binary encoding
+  #[structopt(long = "proof", parse(from_os_str))]
+  proof: PathBuf,
+  /// Context string the proof is bound to
+  #[structopt(long = "context", default_value = "")]
+  context: String,
+  /// Name of the generator set the statement uses
+  #[structopt(long = "generators", default_value = "standard")]
+  generators: String,
+  /// Asset label, for a proof over the asset's amount generator in the
+  /// generator set
+  #[structopt(long = "asset")]
+  asset: Option<String>,
+  /// Identity key, as hex, of the exchange the proof must be claimed by
+  #[structopt(long = "identity")]
+  identity: Option<String>,
+  /// Curve backend: `ristretto` (Keccak-512), `ristretto-sha512`,
+  /// `ristretto-blake2b` or `monero`
+  #[structopt(long = "curve", default_value = "ristretto")]
+  curve: CurveName,
+  /// Issue an audit report signed with --key
+  #[structopt(long = "report")]
+  report: bool,
+  /// File holding the verifier's secret signing key, as hex
+  #[structopt(long = "key", parse(from_os_str))]
+  key: Option<PathBuf>,
+  /// Report format: `text` or `json`
+  #[structopt(long = "format", default_value = "text")]
+  format: Format,
+}
+
+#[derive(Serialize)]
+struct Parameters {
+  curve: &'static str,
+  anon_list_size: usize,
   own_list_size: usize,
-  #[structopt(short = "n", long = "numiter", default_value = "1")]
   num_iter: u32,
+  seed: u64,
+}
+
+#[derive(Serialize)]
+struct Iteration {
+  prove_secs: f64,
+  verify_secs: f64,
+  proof_bytes: usize,
+  verified: bool,
+}
+
+#[derive(Serialize)]
+struct Host {
+  os: &'static str,
+  arch: &'static str,
+  cpus: usize,
+  crate_version: &'static str,
+}
+
+// row of an exported anonymity set, as read by OutputExport
+#[derive(Serialize)]
+struct ExportRow {
+  height: u64,
+  public_key: String,
+  commitment: String,
+}
+
+#[derive(Serialize)]
+struct Report {
+  parameters: Parameters,
+  iterations: Vec<Iteration>,
+  total_secs: f64,
+  average_prove_secs: f64,
+  average_verify_secs: f64,
+  proof_size: Option<MProveSize>,
+  verified: bool,
+  host: Host,
 }
 
 fn main() {
-    // 
+    //
     // cargo run --release --bin mprove_bin 1000 100 -n 10
+    // cargo run --release --bin mprove_bin 1000 100 -n 10 --format json
+    // cargo run --release --bin mprove_bin 100 10 --export out
+    // cargo run --release --bin mprove_bin verify --anon-set out/anon_set.json --proof out/proof.bin --report --key key.hex
     //
     let opt = Opt::from_args();
 
+    if let Some(Command::Verify(ref verify_opt)) = opt.command {
+        match verify_opt.curve {
+          CurveName::Ristretto => verify::<Ristretto>(verify_opt, "ristretto"),
+          CurveName::RistrettoSha512 => verify::<RistrettoSha512>(verify_opt, "ristretto-sha512"),
+          CurveName::RistrettoBlake2b => verify::<RistrettoBlake2b>(verify_opt, "ristretto-blake2b"),
+          CurveName::Monero => verify::<MoneroEd25519>(verify_opt, "monero"),
+        }
+        return;
+    }
+    let sizes = match (opt.anon_list_size, opt.own_list_size) {
+      (Some(anon_list_size), Some(own_list_size)) => (anon_list_size, own_list_size),
+      _ => clap::Error::with_description(
+        "the simulator needs <anon_list_size> and <own_list_size>",
+        clap::ErrorKind::MissingRequiredArgument,
+      ).exit(),
+    };
+
+    if opt.profile.is_some() && !cfg!(feature = "profiling") {
+        eprintln!("--profile needs mprove_bin to be built with `--features profiling`");
+        process::exit(1);
+    }
+
+    match opt.curve {
+      CurveName::Ristretto => run::<Ristretto>(&opt, "ristretto", sizes),
+      CurveName::RistrettoSha512 => run::<RistrettoSha512>(&opt, "ristretto-sha512", sizes),
+      CurveName::RistrettoBlake2b => run::<RistrettoBlake2b>(&opt, "ristretto-blake2b", sizes),
+      CurveName::Monero => run::<MoneroEd25519>(&opt, "monero", sizes),
+    }
+}
+
+fn run<C: Curve>(opt: &Opt, curve: &'static str, (anon_list_size, own_list_size): (usize, usize)) {
     let num_iter = opt.num_iter;
     let mut mprove_proof;
     let mut gen_proof_start;
@@ -30,33 +247,222 @@ fn main() {
     let mut ver_proof_end;
     let mut total_gen_proof_duration = Duration::new(0, 0);
     let mut total_ver_proof_duration = Duration::new(0, 0);
+    let mut iterations: Vec<Iteration> = Vec::with_capacity(num_iter as usize);
+    let mut proof_size = None;
+
+    let seed = opt.seed.unwrap_or_else(rand::random);
+    let Fixture { stmt, witness } = match Fixture::<C>::generate(anon_list_size, own_list_size, seed) {
+      Ok(fixture) => fixture,
+      Err(_) => {
+        eprintln!("Need 1 <= anon_list_size and own_list_size <= anon_list_size");
+        process::exit(1);
+      }
+    };
 
-    let (g, c_vec, p_vec, x_vec, e_vec) = MProve::gen_params(opt.anon_list_size, opt.own_list_size);
+    let identity_key = opt.identity_key.as_ref().map(read_key);
+    let identity = identity_key.map(|x| C::basepoint() * x);
+    // a claimed proof is proved for the claimed statement
+    let stmt = match identity {
+        Some(ref exchange) => stmt.claimed_by(*exchange),
+        None => stmt,
+    };
 
     let sim_start = Instant::now();
+    let mut last_proof = None;
 
-    for _i in 0..num_iter {        
+    for _i in 0..num_iter {
 
         gen_proof_start = Instant::now();
-        mprove_proof = MProve::prove(&g, &c_vec, &p_vec, &x_vec, &e_vec);
+        mprove_proof = match MProveProof::prove(&stmt, &witness) {
+          Ok(proof) => proof,
+          Err(e) => {
+            eprintln!("Proof generation failed: {:?}", e);
+            process::exit(1);
+          }
+        };
+        if let Some(ref x) = identity_key {
+            mprove_proof.sign_identity(&stmt, x).unwrap();
+        }
         gen_proof_end = Instant::now();
-        total_gen_proof_duration += gen_proof_end.duration_since(gen_proof_start);
-  
+        let gen_proof_duration = gen_proof_end.duration_since(gen_proof_start);
+        total_gen_proof_duration += gen_proof_duration;
+
         ver_proof_start = Instant::now();
-        assert!(mprove_proof.verify().is_ok());
+        let verified = mprove_proof.verify_with_identity(&stmt, identity.as_ref()).is_ok();
         ver_proof_end = Instant::now();
-        total_ver_proof_duration += ver_proof_end.duration_since(ver_proof_start);
+        let ver_proof_duration = ver_proof_end.duration_since(ver_proof_start);
+        total_ver_proof_duration += ver_proof_duration;
+
+        let size = mprove_proof.size_in_bytes();
+        proof_size = Some(size);
+
+        iterations.push(Iteration {
+          prove_secs: gen_proof_duration.as_secs_f64(),
+          verify_secs: ver_proof_duration.as_secs_f64(),
+          proof_bytes: size.total(),
+          verified,
+        });
+        last_proof = Some(mprove_proof);
+      }
+
+      let sim_duration = Instant::now().duration_since(sim_start);
+      let avg_gen_proof_duration = total_gen_proof_duration.checked_div(num_iter).unwrap();
+      let avg_ver_proof_duration = total_ver_proof_duration.checked_div(num_iter).unwrap();
+      let verified = iterations.iter().all(|it| it.verified);
+
+      match opt.format {
+        Format::Text => {
+          println!("Total simulation time = {:?}", sim_duration);
+
+          println!("Options = {:?}", opt);
+          println!("Seed = {}", seed);
+          println!("Average proof generation time = {:?}", avg_gen_proof_duration);
+          println!("Average proof verification time = {:?}", avg_ver_proof_duration);
+          if let Some(size) = proof_size {
+            println!("Proof size = {} bytes", size.total());
+            println!("  C_prime_vec = {} bytes", size.C_prime_vec);
+            println!("  gamma_vec = {} bytes", size.gamma_vec);
+            println!("  sigma_vec = {} bytes", size.sigma_vec);
+            println!("  C_res = {} bytes", size.C_res);
+            println!("  identity = {} bytes", size.identity);
+            println!("  header = {} bytes", size.header);
+          }
+          println!("Verification {}", if verified { "succeeded" } else { "FAILED" });
+        }
+        Format::Json => {
+          let report = Report {
+            parameters: Parameters {
+              curve,
+              anon_list_size,
+              own_list_size,
+              num_iter,
+              seed,
+            },
+            iterations,
+            total_secs: sim_duration.as_secs_f64(),
+            average_prove_secs: avg_gen_proof_duration.as_secs_f64(),
+            average_verify_secs: avg_ver_proof_duration.as_secs_f64(),
+            proof_size,
+            verified,
+            host: Host {
+              os: std::env::consts::OS,
+              arch: std::env::consts::ARCH,
+              cpus: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+              crate_version: env!("CARGO_PKG_VERSION"),
+            },
+          };
+          println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        }
+      }
+
+      if let Some(ref path) = opt.profile {
+          write_profile(path);
       }
-  
-      let sim_end = Instant::now();
-      println!("Total simulation time = {:?}", sim_end.duration_since(sim_start));
-  
-      println!("Options = {:?}", opt);
-      println!("Average proof generation time = {:?}",
-        total_gen_proof_duration.checked_div(num_iter).unwrap());
-      println!("Average proof verification time = {:?}",
-        total_ver_proof_duration.checked_div(num_iter).unwrap());
 
+      if let (Some(ref dir), Some(ref proof)) = (&opt.export, &last_proof) {
+          export(dir, &stmt, proof);
+      }
+
+      if !verified {
+          process::exit(1);
+      }
+}
+
+#[cfg(feature = "profiling")]
+fn write_profile(path: &PathBuf) {
+    if let Err(e) = mprove_ristretto::profiling::dump_html(path) {
+        eprintln!("Could not write flamegraph to {}: {}", path.display(), e);
+        process::exit(1);
+    }
+    // keep stdout parseable in json mode
+    eprintln!("Flamegraph written to {}", path.display());
+}
+
+#[cfg(not(feature = "profiling"))]
+fn write_profile(_path: &PathBuf) {}
+
+// Writes the anonymity set and proof where `mprove_bin verify` reads them
+fn export<C: Curve>(dir: &PathBuf, stmt: &MProveStatement<C>, proof: &MProveProof<C>) {
+    let rows: Vec<ExportRow> = stmt
+      .P_vec
+      .iter()
+      .zip(stmt.C_vec.iter())
+      .map(|(p, c)| ExportRow {
+        height: 0,
+        public_key: hex::encode(C::compress(p)),
+        commitment: hex::encode(C::compress(c)),
+      })
+      .collect();
+    let written = fs::create_dir_all(dir)
+      .and_then(|_| fs::write(dir.join("anon_set.json"), serde_json::to_string_pretty(&rows).unwrap()))
+      .and_then(|_| fs::write(dir.join("proof.bin"), proof.to_bytes()));
+    if let Err(e) = written {
+        eprintln!("Could not export to {}: {}", dir.display(), e);
+        process::exit(1);
+    }
+    eprintln!("Anonymity set and proof written to {}", dir.display());
 }
 
+fn verify<C: Curve>(opt: &VerifyOpt, curve: &'static str) {
+    let fail = |what: &str| -> ! {
+        eprintln!("{}", what);
+        process::exit(1);
+    };
 
+    let export = OutputExport::<C>::load(&opt.anon_set)
+      .unwrap_or_else(|e| fail(&format!("Could not read {}: {:?}", opt.anon_set.display(), e)));
+    // the statement must have every output of the set
+    if !export.skipped().is_empty() {
+        fail(&format!("Skipped outputs in {}, rows {:?}", opt.anon_set.display(), export.skipped()));
+    }
+    let positions: Vec<usize> = (0..export.outputs().len()).collect();
+    let generators = Generators::<C>::from_name(opt.generators.as_bytes());
+    let stmt = export
+      .statement_with_generators(&positions, &generators, opt.context.as_bytes())
+      .and_then(|stmt| match opt.asset {
+        // an asset's statement is over its amount generator, unnamed
+        Some(ref label) => MProveStatement::new(stmt.G, generators.asset(label.as_bytes()), stmt.C_vec, stmt.P_vec, &stmt.context),
+        None => Ok(stmt),
+      })
+      .unwrap_or_else(|e| fail(&format!("Invalid anonymity set: {:?}", e)));
+    let bytes = fs::read(&opt.proof)
+      .unwrap_or_else(|e| fail(&format!("Could not read {}: {}", opt.proof.display(), e)));
+    let proof = MProveProof::<C>::from_bytes(&bytes)
+      .unwrap_or_else(|e| fail(&format!("Malformed proof: {:?}", e)));
+    let identity = opt.identity.as_ref().map(|key| {
+      decode_point::<C>(key).unwrap_or_else(|e| fail(&format!("Invalid identity key: {:?}", e)))
+    });
+
+    if !opt.report {
+        match proof.verify_with_identity(&stmt, identity.as_ref()) {
+          Ok(()) => println!("Verification succeeded"),
+          Err(e) => fail(&format!("Verification FAILED: {:?}", e)),
+        }
+        return;
+    }
+
+    let key = read_key(opt.key.as_ref().unwrap_or_else(|| fail("--report needs the verifier's signing key, --key")));
+    let verified_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+    let report = AuditReport::new(&stmt, &proof, identity.as_ref(), curve, verified_at)
+      .unwrap_or_else(|e| fail(&format!("Verification FAILED: {:?}", e)))
+      .sign::<C>(&key);
+    match opt.format {
+      Format::Text => println!("{}", report.summary()),
+      Format::Json => println!("{}", report.to_json()),
+    }
+}
+
+// secret key, as hex, from a file
+fn read_key(path: &PathBuf) -> Scalar {
+    let key = fs::read_to_string(path)
+      .map_err(|e| format!("{}", e))
+      .and_then(|text| decode_scalar(&text).map_err(|e| format!("{:?}", e)));
+    match key {
+      Ok(key) => key,
+      Err(e) => {
+        eprintln!("Could not read key {}: {}", path.display(), e);
+        process::exit(1);
+      }
+    }
+}
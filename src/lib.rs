@@ -12,18 +12,51 @@ This file is part of mprove library
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
+extern crate serde_json;
 
 extern crate rand;
+extern crate rand_chacha;
+extern crate rand_distr;
+#[cfg(feature = "profiling")]
 extern crate flame;
 extern crate curve25519_dalek;
 extern crate sha2;
 extern crate sha3;
+extern crate hex;
+extern crate blake2;
+
+#[cfg(test)]
+extern crate proptest;
+
+// Opens a flame span that lasts until the end of the enclosing block.
+// Expands to nothing unless the `profiling` feature is enabled.
+#[cfg(feature = "profiling")]
+macro_rules! profile_span {
+    ($name:expr) => {
+        let _fg = ::flame::start_guard($name);
+    };
+}
 
+#[cfg(not(feature = "profiling"))]
+macro_rules! profile_span {
+    ($name:expr) => {};
+}
 
 pub mod proofs;
+#[cfg(feature = "profiling")]
+pub mod profiling;
 
 #[derive(Copy, PartialEq, Eq, Clone, Debug)]
 pub enum Errors {
     MProveSigsError,
     MProveError,
+    MProveWitnessError,
+    MProveOpeningError,
+    MProveImportError,
+    MProveMissingOutputError,
+    MProveSamplerError,
+    MProveIoError,
+    MProveCheckpointError,
+    MProveThresholdError,
+    MProveIdentityError,
 }
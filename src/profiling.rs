@@ -0,0 +1,31 @@
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mprove library
+(<add a link to github>)
+
+*/
+
+// Flamegraph output for the spans recorded by `profile_span!`.
+// Only compiled with the `profiling` feature.
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use flame;
+
+/// Writes the recorded spans as an HTML flamegraph to `path`.
+pub fn dump_html<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    flame::dump_html(File::create(path)?)
+}
+
+/// Writes the recorded spans as JSON to `path`.
+pub fn dump_json<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    flame::dump_json(&mut File::create(path)?)
+}
+
+/// Discards all spans recorded so far.
+pub fn clear() {
+    flame::clear();
+}
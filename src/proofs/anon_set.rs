@@ -0,0 +1,344 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Anonymity sets built from a local export of the Monero output set.
+//
+// The export lists, for every output, the height of the block it was mined
+// in, its one-time public key P and its RingCT commitment C, as hex, under
+// the header `height,public_key,commitment` (see export for the formats).
+// Pre-RingCT outputs must already have been given their zeroCommit(amount)
+// commitments by the exporter.
+//
+// Rows whose keys are 32 bytes but not valid points, such as the handful of
+// torsioned output keys on the Monero chain, cannot be in an anonymity set.
+// They are skipped and their row numbers kept, so positions in an export are
+// positions among the decoded outputs, not rows of the file. Keys that are
+// not 32 bytes of hex mean a damaged export, which is rejected.
+//
+// A one-time key can also appear more than once on chain (the burning bug).
+// Only one of its outputs can ever be spent, and listing it twice in a set
+// would let it be counted twice, so only its first row is kept and the
+// later ones are skipped.
+use Errors::{self, MProveImportError};
+use proofs::backend::{Curve, MoneroEd25519};
+use proofs::export::{csv_records, decode_key, json_records, load_records, ExportRecord};
+use proofs::generators::Generators;
+use proofs::mprove::MProveStatement;
+
+use rand::seq::index;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use std::collections::HashSet;
+use std::path::Path;
+
+// One output of the export, with its keys decoded
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportedOutput<C: Curve = MoneroEd25519> {
+    pub height: u64,
+    pub P: C::Point,
+    pub C: C::Point,
+}
+
+// Raw row of the export, before the keys are decoded
+#[derive(Deserialize)]
+struct OutputRecord {
+    height: u64,
+    public_key: String,
+    commitment: String,
+}
+
+impl ExportRecord for OutputRecord {
+    const HEADER: &'static [&'static str] = &["height", "public_key", "commitment"];
+
+    fn from_fields(fields: &[&str]) -> Result<OutputRecord, Errors> {
+        Ok(OutputRecord {
+            height: fields[0].parse().map_err(|_| MProveImportError)?,
+            public_key: fields[1].to_string(),
+            commitment: fields[2].to_string(),
+        })
+    }
+}
+
+// Which outputs of the export may enter the anonymity set. Heights are
+// inclusive. The age of an output is tip_height - height, where tip_height
+// defaults to the highest height in the export.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OutputFilter {
+    pub min_height: Option<u64>,
+    pub max_height: Option<u64>,
+    pub min_age: u64,
+    pub tip_height: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct OutputExport<C: Curve = MoneroEd25519> {
+    outputs: Vec<ExportedOutput<C>>,
+    skipped: Vec<usize>,
+}
+
+impl<C: Curve> OutputExport<C> {
+    pub fn new(outputs: Vec<ExportedOutput<C>>) -> OutputExport<C> {
+        OutputExport { outputs, skipped: Vec::new() }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<OutputExport<C>, Errors> {
+        OutputExport::from_records(load_records(path)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<OutputExport<C>, Errors> {
+        OutputExport::from_records(json_records(text)?)
+    }
+
+    pub fn from_csv(text: &str) -> Result<OutputExport<C>, Errors> {
+        OutputExport::from_records(csv_records(text)?)
+    }
+
+    fn from_records(records: Vec<OutputRecord>) -> Result<OutputExport<C>, Errors> {
+        let mut outputs = Vec::with_capacity(records.len());
+        let mut skipped = Vec::new();
+        let mut keys: HashSet<[u8; 32]> = HashSet::new();
+        for (row, record) in records.iter().enumerate() {
+            match (decode_key::<C>(&record.public_key)?, decode_key::<C>(&record.commitment)?) {
+                (Some(P), Some(C)) if keys.insert(C::compress(&P)) => {
+                    outputs.push(ExportedOutput { height: record.height, P, C })
+                }
+                _ => skipped.push(row),
+            }
+        }
+        Ok(OutputExport { outputs, skipped })
+    }
+
+    pub fn outputs(&self) -> &[ExportedOutput<C>] {
+        &self.outputs
+    }
+
+    // rows of the export, counted from 0 after the header, whose keys are
+    // not points or whose one-time key was already listed
+    pub fn skipped(&self) -> &[usize] {
+        &self.skipped
+    }
+
+    // highest block height in the export
+    pub fn tip_height(&self) -> Option<u64> {
+        self.outputs.iter().map(|out| out.height).max()
+    }
+
+    // Positions in the export of the outputs passing the filter, in export
+    // order. map_or rather than is_none_or, which needs Rust 1.82.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn filter(&self, filter: &OutputFilter) -> Vec<usize> {
+        let tip = match filter.tip_height.or_else(|| self.tip_height()) {
+            Some(tip) => tip,
+            None => return Vec::new(),
+        };
+
+        (0..self.outputs.len())
+            .filter(|&k| {
+                let height = self.outputs[k].height;
+                filter.min_height.map_or(true, |min| height >= min)
+                    && filter.max_height.map_or(true, |max| height <= max)
+                    && height <= tip
+                    && tip - height >= filter.min_age
+            })
+            .collect()
+    }
+
+    // Deterministically picks n of the outputs passing the filter, seeded by
+    // `seed`. The same export, filter and seed always give the same
+    // positions, returned in export order.
+    pub fn select(
+        &self,
+        filter: &OutputFilter,
+        n: usize,
+        seed: [u8; 32],
+    ) -> Result<Vec<usize>, Errors> {
+
+        let candidates = self.filter(filter);
+        if n == 0 || n > candidates.len() {
+            return Err(MProveImportError);
+        }
+
+        let mut rng = ChaCha20Rng::from_seed(seed);
+        let mut picked: Vec<usize> = index::sample(&mut rng, candidates.len(), n)
+            .into_iter()
+            .map(|k| candidates[k])
+            .collect();
+        picked.sort_unstable();
+        Ok(picked)
+    }
+
+    // Statement whose anonymity set is the outputs at `positions`, in that
+    // order, with the backend's generators
+    pub fn statement(
+        &self,
+        positions: &[usize],
+        context: &[u8],
+    ) -> Result<MProveStatement<C>, Errors> {
+        self.statement_with_generators(positions, &Generators::standard(), context)
+    }
+
+    // Same as statement, over the G and H of a named generator set
+    pub fn statement_with_generators(
+        &self,
+        positions: &[usize],
+        generators: &Generators<C>,
+        context: &[u8],
+    ) -> Result<MProveStatement<C>, Errors> {
+
+        if positions.iter().any(|&k| k >= self.outputs.len()) {
+            return Err(MProveImportError);
+        }
+        let C_vec = positions.iter().map(|&k| self.outputs[k].C).collect();
+        let P_vec = positions.iter().map(|&k| self.outputs[k].P).collect();
+        MProveStatement::from_generators(generators, C_vec, P_vec, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::EIGHT_TORSION;
+    use curve25519_dalek::scalar::Scalar;
+    use std::fs;
+
+    // export of `len` random outputs, two per block starting at height 100
+    pub fn test_export(len: usize) -> (String, String) {
+        let mut rng = rand::thread_rng();
+        let rows: Vec<(u64, String, String)> = (0..len)
+            .map(|k| {
+                let P = MoneroEd25519::random_point(&mut rng);
+                let C = MoneroEd25519::basepoint() * Scalar::random(&mut rng);
+                (
+                    100 + (k / 2) as u64,
+                    hex::encode(MoneroEd25519::compress(&P)),
+                    hex::encode(MoneroEd25519::compress(&C)),
+                )
+            })
+            .collect();
+
+        let mut csv = String::from("# output export\nheight,public_key,commitment\n");
+        for (height, P, C) in rows.iter() {
+            csv.push_str(&format!("{},{},{}\n", height, P, C));
+        }
+        let json = rows
+            .iter()
+            .map(|(height, P, C)| {
+                format!(r#"{{"height":{},"public_key":"{}","commitment":"{}"}}"#, height, P, C)
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        let json = format!("[{}]", json);
+        (csv, json)
+    }
+
+    #[test]
+    pub fn load_export(){
+        let (csv, json) = test_export(40);
+        let dir = std::env::temp_dir();
+        let csv_path = dir.join(format!("mprove_export_{}.csv", std::process::id()));
+        let json_path = dir.join(format!("mprove_export_{}.json", std::process::id()));
+        fs::write(&csv_path, &csv).unwrap();
+        fs::write(&json_path, &json).unwrap();
+
+        let export = OutputExport::<MoneroEd25519>::load(&csv_path).unwrap();
+        let from_json = OutputExport::<MoneroEd25519>::load(&json_path).unwrap();
+        fs::remove_file(&csv_path).unwrap();
+        fs::remove_file(&json_path).unwrap();
+        assert_eq!(export.outputs(), from_json.outputs());
+        assert_eq!(export.outputs().len(), 40);
+        assert_eq!(export.tip_height(), Some(119));
+
+        // heights 105..=112, at least 9 blocks below the tip
+        let filter = OutputFilter {
+            min_height: Some(105),
+            max_height: Some(112),
+            min_age: 9,
+            ..OutputFilter::default()
+        };
+        assert_eq!(export.filter(&filter), (10..22).collect::<Vec<usize>>());
+
+        // selection is reproducible from the seed
+        let picked = export.select(&filter, 5, [7u8; 32]).unwrap();
+        assert_eq!(picked, export.select(&filter, 5, [7u8; 32]).unwrap());
+        assert!(picked.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(picked.iter().all(|k| (10..22).contains(k)));
+        assert_eq!(export.select(&filter, 13, [7u8; 32]), Err(MProveImportError));
+
+        let stmt = export.statement(&picked, b"export").unwrap();
+        assert_eq!(stmt.len(), 5);
+        assert_eq!(stmt.P_vec[0], export.outputs()[picked[0]].P);
+        assert_eq!(stmt.H, MoneroEd25519::amount_generator());
+
+        let audit = Generators::<MoneroEd25519>::from_name(b"audit");
+        let named = export.statement_with_generators(&picked, &audit, b"export").unwrap();
+        assert_eq!(named.H, audit.H());
+        assert_eq!(named.generators, Some(b"audit".to_vec()));
+        assert_eq!(named.C_vec, stmt.C_vec);
+    }
+
+    #[test]
+    pub fn malformed_export(){
+        let (csv, _) = test_export(2);
+        assert!(OutputExport::<MoneroEd25519>::from_csv(&csv).is_ok());
+
+        // wrong header, bad height, missing column
+        let bad = [
+            csv.replace("public_key", "key"),
+            csv.replacen("100,", "x,", 1),
+            "height,public_key,commitment\n100,abcd\n".to_string(),
+        ];
+        for text in bad.iter() {
+            assert_eq!(OutputExport::<MoneroEd25519>::from_csv(text).err(), Some(MProveImportError));
+        }
+        assert!(OutputExport::<MoneroEd25519>::from_json("{}").is_err());
+        assert!(OutputExport::<MoneroEd25519>::load("/nonexistent/export.csv").is_err());
+    }
+
+    #[test]
+    pub fn skip_undecodable_rows(){
+        let (csv, json) = test_export(6);
+        let rows: Vec<&str> = csv.lines().skip(2).collect();
+
+        // a torsioned output key, as on the real chain
+        let P = MoneroEd25519::basepoint() * Scalar::random(&mut rand::thread_rng());
+        let torsioned = hex::encode((P + EIGHT_TORSION[1]).compress().as_bytes());
+        let fields: Vec<&str> = rows[1].split(',').collect();
+        let bad = csv.replace(rows[1], &format!("{},{},{}", fields[0], torsioned, fields[2]));
+
+        let export = OutputExport::<MoneroEd25519>::from_csv(&bad).unwrap();
+        assert_eq!(export.skipped(), &[1]);
+        assert_eq!(export.outputs().len(), 5);
+        let good = OutputExport::<MoneroEd25519>::from_json(&json).unwrap();
+        assert!(good.skipped().is_empty());
+        assert_eq!(export.outputs()[0], good.outputs()[0]);
+        assert_eq!(export.outputs()[1..], good.outputs()[2..]);
+
+        // a one-time key listed again keeps only its first row
+        let (earlier, later): (Vec<&str>, Vec<&str>) = (rows[2].split(',').collect(), rows[3].split(',').collect());
+        let repeated = csv.replace(rows[3], &format!("{},{},{}", later[0], earlier[1], later[2]));
+        let export = OutputExport::<MoneroEd25519>::from_csv(&repeated).unwrap();
+        assert_eq!(export.skipped(), &[3]);
+        assert_eq!(export.outputs()[2], good.outputs()[2]);
+        let positions: Vec<usize> = (0..export.outputs().len()).collect();
+        assert!(export.statement(&positions, b"export").is_ok());
+
+        // short, truncated or non-hex keys are a damaged export, not skipped
+        let damaged = [
+            format!("{}120,abcd,abcd\n", csv),
+            csv[..csv.len() - 10].to_string(),
+            csv.replace(fields[2], &format!("zz{}", &fields[2][2..])),
+        ];
+        for text in damaged.iter() {
+            assert_eq!(OutputExport::<MoneroEd25519>::from_csv(text).err(), Some(MProveImportError));
+        }
+    }
+}
@@ -0,0 +1,302 @@
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Arithmetic modulo p = 2^255 - 19, just enough to port Monero's
+// ge_fromfe_frombytes_vartime. curve25519-dalek keeps its field elements
+// private, so this is a small, variable-time, u128-based implementation.
+// It is only ever used on public data (hashes of public keys).
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+// p = 2^255 - 19, little-endian 64-bit limbs
+const P: [u64; 4] = [
+    0xffff_ffff_ffff_ffed,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x7fff_ffff_ffff_ffff,
+];
+
+// (p - 5) / 8 = 2^252 - 3
+const P_MINUS_5_DIV_8: [u64; 4] = [
+    0xffff_ffff_ffff_fffd,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x0fff_ffff_ffff_ffff,
+];
+
+// (p + 3) / 8 = 2^252 - 2
+const P_PLUS_3_DIV_8: [u64; 4] = [
+    0xffff_ffff_ffff_fffe,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x0fff_ffff_ffff_ffff,
+];
+
+// (p - 1) / 4 = 2^253 - 5
+const P_MINUS_1_DIV_4: [u64; 4] = [
+    0xffff_ffff_ffff_fffb,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x1fff_ffff_ffff_ffff,
+];
+
+// p - 2
+const P_MINUS_2: [u64; 4] = [
+    0xffff_ffff_ffff_ffeb,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x7fff_ffff_ffff_ffff,
+];
+
+// Field element, always kept fully reduced (< p)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FieldElement([u64; 4]);
+
+impl FieldElement {
+    pub fn zero() -> FieldElement {
+        FieldElement([0, 0, 0, 0])
+    }
+
+    pub fn one() -> FieldElement {
+        FieldElement([1, 0, 0, 0])
+    }
+
+    pub fn from_u64(x: u64) -> FieldElement {
+        FieldElement([x, 0, 0, 0])
+    }
+
+    // Interprets all 256 bits of `bytes` (little endian) and reduces mod p
+    pub fn from_bytes(bytes: &[u8; 32]) -> FieldElement {
+        let mut limbs = [0u64; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[i * 8..(i + 1) * 8]);
+            *limb = u64::from_le_bytes(word);
+        }
+        FieldElement(limbs).reduce()
+    }
+
+    pub fn to_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for i in 0..4 {
+            bytes[i * 8..(i + 1) * 8].copy_from_slice(&self.0[i].to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0, 0, 0, 0]
+    }
+
+    // "negative" in the ref10 sense: the canonical encoding is odd
+    pub fn is_negative(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+
+    pub fn square(&self) -> FieldElement {
+        *self * *self
+    }
+
+    pub fn pow(&self, exp: &[u64; 4]) -> FieldElement {
+        let mut result = FieldElement::one();
+        for i in (0..4).rev() {
+            for bit in (0..64).rev() {
+                result = result.square();
+                if (exp[i] >> bit) & 1 == 1 {
+                    result = result * *self;
+                }
+            }
+        }
+        result
+    }
+
+    pub fn invert(&self) -> FieldElement {
+        self.pow(&P_MINUS_2)
+    }
+
+    // sqrt(-1) = 2^((p - 1) / 4)
+    pub fn sqrt_m1() -> FieldElement {
+        FieldElement::from_u64(2).pow(&P_MINUS_1_DIV_4)
+    }
+
+    // (u / v)^((p + 3) / 8), computed as u v^3 (u v^7)^((p - 5) / 8)
+    pub fn div_pow_m1(u: &FieldElement, v: &FieldElement) -> FieldElement {
+        let v3 = v.square() * *v;
+        let v7 = v3.square() * *v;
+        *u * v3 * (*u * v7).pow(&P_MINUS_5_DIV_8)
+    }
+
+    // Some square root of self, if self is a square
+    pub fn sqrt(&self) -> Option<FieldElement> {
+        let r = self.pow(&P_PLUS_3_DIV_8);
+        if r.square() == *self {
+            Some(r)
+        } else {
+            let r = r * FieldElement::sqrt_m1();
+            if r.square() == *self {
+                Some(r)
+            } else {
+                None
+            }
+        }
+    }
+
+    // conditionally subtracts p until the value is canonical
+    fn reduce(self) -> FieldElement {
+        let mut r = self.0;
+        while !lt(&r, &P) {
+            r = sub_limbs(&r, &P).0;
+        }
+        FieldElement(r)
+    }
+}
+
+// a < b on little-endian limbs
+fn lt(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    for i in (0..4).rev() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+    }
+    false
+}
+
+// a - b, and whether it borrowed
+fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut r = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (d1, b1) = a[i].overflowing_sub(b[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow as u64);
+        r[i] = d2;
+        borrow = b1 || b2;
+    }
+    (r, borrow)
+}
+
+impl Add for FieldElement {
+    type Output = FieldElement;
+
+    fn add(self, other: FieldElement) -> FieldElement {
+        // both inputs are < p < 2^255, so the sum fits in 256 bits
+        let mut r = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, r_i) in r.iter_mut().enumerate() {
+            let t = self.0[i] as u128 + other.0[i] as u128 + carry;
+            *r_i = t as u64;
+            carry = t >> 64;
+        }
+        FieldElement(r).reduce()
+    }
+}
+
+impl Sub for FieldElement {
+    type Output = FieldElement;
+
+    fn sub(self, other: FieldElement) -> FieldElement {
+        let (r, borrow) = sub_limbs(&self.0, &other.0);
+        if borrow {
+            // r wrapped around 2^256; adding p wraps it back into [0, p)
+            let mut s = [0u64; 4];
+            let mut carry = 0u128;
+            for (i, s_i) in s.iter_mut().enumerate() {
+                let t = r[i] as u128 + P[i] as u128 + carry;
+                *s_i = t as u64;
+                carry = t >> 64;
+            }
+            FieldElement(s)
+        } else {
+            FieldElement(r)
+        }
+    }
+}
+
+impl Neg for FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> FieldElement {
+        FieldElement::zero() - self
+    }
+}
+
+impl Mul for FieldElement {
+    type Output = FieldElement;
+
+    fn mul(self, other: FieldElement) -> FieldElement {
+        // schoolbook 256x256 -> 512 bit product
+        let mut t = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let v = t[i + j] as u128 + (self.0[i] as u128) * (other.0[j] as u128) + carry;
+                t[i + j] = v as u64;
+                carry = v >> 64;
+            }
+            t[i + 4] = carry as u64;
+        }
+
+        // 2^256 = 38 mod p
+        let mut r = [0u64; 4];
+        let mut carry = 0u128;
+        for i in 0..4 {
+            let v = t[i] as u128 + (t[i + 4] as u128) * 38 + carry;
+            r[i] = v as u64;
+            carry = v >> 64;
+        }
+        let mut carry = carry * 38;
+        for limb in r.iter_mut() {
+            let v = *limb as u128 + carry;
+            *limb = v as u64;
+            carry = v >> 64;
+        }
+        if carry != 0 {
+            // only reachable when r wrapped to a small value
+            r[0] += 38;
+        }
+        FieldElement(r).reduce()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn field_arithmetic(){
+        let p_minus_1 = -FieldElement::one();
+        assert_eq!(p_minus_1 + FieldElement::one(), FieldElement::zero());
+        assert_eq!(p_minus_1 * p_minus_1, FieldElement::one());
+
+        // 2^255 = 19 mod p
+        let mut bytes = [0u8; 32];
+        bytes[31] = 0x80;
+        assert_eq!(FieldElement::from_bytes(&bytes), FieldElement::from_u64(19));
+        assert_eq!(FieldElement::from_bytes(&[0xff; 32]).to_bytes()[0], 0x25);
+
+        let i = FieldElement::sqrt_m1();
+        assert_eq!(i.square(), p_minus_1);
+
+        let x = FieldElement::from_u64(123456789);
+        assert_eq!(x * x.invert(), FieldElement::one());
+        let r = x.square().sqrt().unwrap();
+        assert!(r == x || r == -x);
+        assert_eq!(FieldElement::div_pow_m1(&x.square(), &FieldElement::one()).square(), x.square());
+    }
+
+    #[test]
+    pub fn field_constants(){
+        // fe_sqrtm1 and fe_d of Monero's crypto-ops-data.c, little endian
+        assert_eq!(
+            hex::encode(FieldElement::sqrt_m1().to_bytes()),
+            "b0a00e4a271beec478e42fad0618432fa7d7fb3d99004d2b0bdfc14f8024832b"
+        );
+        let d = -FieldElement::from_u64(121665) * FieldElement::from_u64(121666).invert();
+        assert_eq!(hex::encode(d.to_bytes()), "a3785913ca4deb75abd841414d0a700098e879777940c78c73fe6f2bee6c0352");
+    }
+}
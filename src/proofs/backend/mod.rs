@@ -0,0 +1,80 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Curve backends for the ring signatures and MProve.
+//
+// A backend fixes the prime-order group the protocol runs in, its
+// generators, point encoding, and the hash-to-point (H_p) and
+// hash-to-scalar (H_s) functions. Scalars are always curve25519 scalars,
+// since both supported groups have prime order
+// l = 2^252 + 27742317777372353535851937790883648493.
+use std::fmt::Debug;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use curve25519_dalek::scalar::Scalar;
+use rand::{CryptoRng, RngCore};
+
+mod field;
+pub mod monero;
+pub mod ristretto;
+
+pub use self::monero::MoneroEd25519;
+pub use self::ristretto::{Ristretto, RistrettoBlake2b, RistrettoSha512};
+
+pub trait Curve: Copy + Clone + Debug + Default + PartialEq {
+    type Point: Copy
+        + Debug
+        + PartialEq
+        + Eq
+        + Add<Output = Self::Point>
+        + Sub<Output = Self::Point>
+        + Neg<Output = Self::Point>
+        + Mul<Scalar, Output = Self::Point>;
+
+    // generator for keys and blinding factors
+    fn basepoint() -> Self::Point;
+
+    // generator for amounts in commitments
+    fn amount_generator() -> Self::Point;
+
+    fn identity() -> Self::Point;
+
+    fn random_point<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Point;
+
+    fn compress(P: &Self::Point) -> [u8; 32];
+
+    // None unless `bytes` is the canonical encoding of a point in the
+    // prime-order subgroup
+    fn decompress(bytes: &[u8]) -> Option<Self::Point>;
+
+    // H_p: hash to a point of the prime-order subgroup
+    fn hash_to_point(bytes: &[u8]) -> Self::Point;
+
+    // H_s: hash to a scalar
+    fn hash_to_scalar(bytes: &[u8]) -> Scalar;
+
+    fn in_prime_order_subgroup(P: &Self::Point) -> bool;
+
+    fn vartime_multiscalar_mul(scalars: &[Scalar], points: &[Self::Point]) -> Self::Point;
+
+    // a*A + b*basepoint
+    fn vartime_double_scalar_mul_basepoint(a: &Scalar, A: &Self::Point, b: &Scalar) -> Self::Point;
+
+    // base point of the key image of a one-time address P, I = x * H_p(P)
+    fn key_image_base(P: &Self::Point) -> Self::Point {
+        Self::hash_to_point(&Self::compress(P))
+    }
+
+    // sum of a slice of points
+    fn sum(points: &[Self::Point]) -> Self::Point {
+        points.iter().fold(Self::identity(), |acc, P| acc + *P)
+    }
+}
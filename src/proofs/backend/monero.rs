@@ -0,0 +1,241 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Monero-compatible backend: Ed25519 points, Keccak-256 H_s (cn_fast_hash
+// reduced mod l), Monero's hash_to_ec for H_p, and the RingCT generator H.
+//
+// Ed25519 has cofactor 8. Decoded points are only accepted if they are
+// torsion-free, and H_p multiplies by 8 like hash_to_ec does, so key images
+// I = x * hash_to_ec(P) always land in the prime-order subgroup.
+use proofs::backend::field::FieldElement;
+use proofs::backend::Curve;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, VartimeMultiscalarMul};
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Keccak256};
+
+// H from Monero's rctTypes.h, 8 * ge_frombytes(cn_fast_hash(G))
+const MONERO_H: [u8; 32] = [
+    0x8b, 0x65, 0x59, 0x70, 0x15, 0x37, 0x99, 0xaf,
+    0x2a, 0xea, 0xdc, 0x9f, 0xf1, 0xad, 0xd0, 0xea,
+    0x6c, 0x72, 0x51, 0xd5, 0x41, 0x54, 0xcf, 0xa9,
+    0x2c, 0x17, 0x3a, 0x0d, 0xd3, 0x9c, 0x1f, 0x94,
+];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MoneroEd25519;
+
+// cn_fast_hash
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&Keccak256::digest(bytes));
+    out
+}
+
+// Monero's hash_to_ec: 8 * ge_fromfe_frombytes_vartime(cn_fast_hash(bytes))
+pub fn hash_to_ec(bytes: &[u8]) -> EdwardsPoint {
+    ge_fromfe_frombytes(&keccak256(bytes)).mul_by_cofactor()
+}
+
+// Port of ge_fromfe_frombytes_vartime from Monero's crypto-ops.c, an
+// Elligator-style map from 32 bytes (read as a field element) to the curve.
+// Variable names follow the C code.
+fn ge_fromfe_frombytes(s: &[u8; 32]) -> EdwardsPoint {
+    let (x, y) = ge_fromfe_frombytes_affine(s);
+
+    let mut bytes = y.to_bytes();
+    bytes[31] |= (x.is_negative() as u8) << 7;
+    CompressedEdwardsY(bytes)
+        .decompress()
+        .expect("ge_fromfe_frombytes always yields a curve point")
+}
+
+// affine (x, y) of ge_fromfe_frombytes_vartime
+fn ge_fromfe_frombytes_affine(s: &[u8; 32]) -> (FieldElement, FieldElement) {
+    let A = FieldElement::from_u64(486662);
+    let sqrtm1 = FieldElement::sqrt_m1();
+    let one = FieldElement::one();
+
+    let u = FieldElement::from_bytes(s);
+    let v = u.square() + u.square(); // 2 * u^2
+    let w = v + one; // w = 2 * u^2 + 1
+    let mut x = w.square() - A.square() * v; // x = w^2 - 2 * A^2 * u^2
+    let mut X = FieldElement::div_pow_m1(&w, &x); // (w / x)^(m + 1)
+    x = X.square() * x;
+
+    let AA2 = A * (A + FieldElement::from_u64(2)); // A * (A + 2)
+    let mut z = -A;
+    let sign;
+    if (w - x).is_zero() || (w + x).is_zero() {
+        if (w - x).is_zero() {
+            X = X * fffb(AA2 + AA2); // sqrt(2 * A * (A + 2))
+        } else {
+            X = X * fffb(-(AA2 + AA2)); // sqrt(-2 * A * (A + 2))
+        }
+        X = X * u; // u * sqrt(2 * A * (A + 2) * w / x)
+        z = z * v; // -2 * A * u^2
+        sign = false;
+    } else {
+        x = x * sqrtm1;
+        if (w - x).is_zero() {
+            X = X * fffb(sqrtm1 * AA2); // sqrt(sqrtm1 * A * (A + 2))
+        } else {
+            debug_assert!((w + x).is_zero());
+            X = X * fffb(-sqrtm1 * AA2); // sqrt(-sqrtm1 * A * (A + 2))
+        }
+        // X = sqrt(A * (A + 2) * w / x), z = -A
+        sign = true;
+    }
+
+    if X.is_negative() != sign {
+        X = -X;
+    }
+
+    // projective (X * Z : Y : Z) with Z = z + w, Y = z - w
+    (X, (z - w) * (z + w).invert())
+}
+
+// The fe_fffb* constants of crypto-ops-data.c. They are only defined up to
+// sign, which does not matter since X is normalised afterwards.
+fn fffb(square: FieldElement) -> FieldElement {
+    square.sqrt().expect("fffb constants are squares")
+}
+
+impl Curve for MoneroEd25519 {
+    type Point = EdwardsPoint;
+
+    fn basepoint() -> EdwardsPoint {
+        constants::ED25519_BASEPOINT_POINT
+    }
+
+    fn amount_generator() -> EdwardsPoint {
+        CompressedEdwardsY(MONERO_H).decompress().unwrap()
+    }
+
+    fn identity() -> EdwardsPoint {
+        EdwardsPoint::identity()
+    }
+
+    fn random_point<R: RngCore + CryptoRng>(rng: &mut R) -> EdwardsPoint {
+        &Scalar::random(rng) * &constants::ED25519_BASEPOINT_TABLE
+    }
+
+    fn compress(P: &EdwardsPoint) -> [u8; 32] {
+        P.compress().to_bytes()
+    }
+
+    fn decompress(bytes: &[u8]) -> Option<EdwardsPoint> {
+        if bytes.len() != 32 {
+            return None;
+        }
+        let P = CompressedEdwardsY::from_slice(bytes).decompress()?;
+        // reject non-canonical encodings and points with a torsion component
+        if P.compress().as_bytes() != bytes || !P.is_torsion_free() {
+            return None;
+        }
+        Some(P)
+    }
+
+    fn hash_to_point(bytes: &[u8]) -> EdwardsPoint {
+        hash_to_ec(bytes)
+    }
+
+    fn hash_to_scalar(bytes: &[u8]) -> Scalar {
+        Scalar::from_bytes_mod_order(keccak256(bytes))
+    }
+
+    fn in_prime_order_subgroup(P: &EdwardsPoint) -> bool {
+        P.is_torsion_free()
+    }
+
+    fn vartime_multiscalar_mul(scalars: &[Scalar], points: &[EdwardsPoint]) -> EdwardsPoint {
+        EdwardsPoint::vartime_multiscalar_mul(scalars.iter(), points.iter())
+    }
+
+    fn vartime_double_scalar_mul_basepoint(a: &Scalar, A: &EdwardsPoint, b: &Scalar) -> EdwardsPoint {
+        EdwardsPoint::vartime_double_scalar_mul_basepoint(a, A, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(hex: &str) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&hex::decode(hex).unwrap());
+        out
+    }
+
+    #[test]
+    pub fn monero_generators(){
+        // H = 8 * ge_frombytes(cn_fast_hash(G))
+        let G = MoneroEd25519::basepoint();
+        let H = CompressedEdwardsY(keccak256(G.compress().as_bytes()))
+            .decompress()
+            .unwrap()
+            .mul_by_cofactor();
+        assert_eq!(H, MoneroEd25519::amount_generator());
+        assert!(MoneroEd25519::in_prime_order_subgroup(&H));
+        assert_eq!(hex::encode(H.compress().as_bytes()), "8b655970153799af2aeadc9ff1add0ea6c7251d54154cfa92c173a0dd39c1f94");
+    }
+
+    #[test]
+    pub fn monero_known_answers(){
+        // lines of tests/crypto/tests.txt in the Monero repository
+
+        // hash_to_point <hash> <point>: ge_fromfe_frombytes_vartime alone
+        let hash_to_point = [
+            ("83efb774657700e37291f4b8dd10c839d1c739fd135c07a2fd7382334dafdd6a", "2789ecbaf36e4fcb41c6157228001538b40ca379464b718d830c58caae7ea4ca"),
+            ("5c380f98794ab7a9be7c2d3259b92772125ce93527be6a76210631fdd8001498", "31a1feb4986d42e2137ae061ea031838d24fa523234954cf8860bcd42421ae94"),
+            ("4775d39f91a466262f0ccf21f5a7ee446f79a05448861e212be063a1063298f0", "897b3589f29ea40e576a91506d9aeca4c05a494922a80de57276f4b40c0a98bc"),
+        ];
+        for (hash, point) in hash_to_point.iter() {
+            assert_eq!(ge_fromfe_frombytes(&bytes(hash)).compress().to_bytes(), bytes(point));
+        }
+
+        // hash_to_ec <key> <point>
+        let hash_to_ec_vectors = [
+            ("da66e9ba613919dec28ef367a125bb310d6d83fb9052e71034164b6dc4f392d0", "52b3f38753b4e13b74624862e253072cf12f745d43fcfafbe8c217701a6e5875"),
+            ("a7fbdeeccb597c2d5fdaf2ea2e10cbfcd26b5740903e7f6d46bcbf9a90384fc6", "f055ba2d0d9828ce2e203d9896bfda494d7830e7e3a27fa27d5eaa825a79a19c"),
+        ];
+        for (key, point) in hash_to_ec_vectors.iter() {
+            assert_eq!(hash_to_ec(&bytes(key)).compress().to_bytes(), bytes(point));
+        }
+
+        // generate_key_image <public key> <secret key> <key image>
+        let P = MoneroEd25519::decompress(&bytes("e46b60ebfe610b8ba761032018471e5719bb77ea1cd945475c4a4abe7224bfd0")).unwrap();
+        let x = Scalar::from_canonical_bytes(bytes("981d477fb18897fa1f784c89721a9d600bf283f06b89cb018a077f41dcefef0f")).unwrap();
+        assert_eq!(MoneroEd25519::compress(&(MoneroEd25519::key_image_base(&P) * x)), bytes("a637203ec41eab772532d30420eac80612fce8e44f1758bc7e2cb1bdda815887"));
+    }
+
+    #[test]
+    pub fn hash_to_ec_on_curve(){
+        // the affine point produced by ge_fromfe_frombytes satisfies
+        // -x^2 + y^2 = 1 + d x^2 y^2 whatever the input
+        let d = -FieldElement::from_u64(121665) * FieldElement::from_u64(121666).invert();
+        for i in 0..64u8 {
+            let (x, y) = ge_fromfe_frombytes_affine(&keccak256(&[i]));
+            assert_eq!(-x.square() + y.square(), FieldElement::one() + d * x.square() * y.square());
+
+            let Q = hash_to_ec(&[i]);
+            assert!(MoneroEd25519::in_prime_order_subgroup(&Q));
+            assert_eq!(MoneroEd25519::decompress(&MoneroEd25519::compress(&Q)), Some(Q));
+        }
+
+        // torsion points do not decode
+        let T = constants::EIGHT_TORSION[1];
+        assert_eq!(MoneroEd25519::decompress(T.compress().as_bytes()), None);
+    }
+}
@@ -0,0 +1,160 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// The original backend: the Ristretto group, Keccak-512 for H_p and H_s,
+// and H derived by hashing the basepoint with SHA-512.
+//
+// The digest used for H_p and H_s is a type parameter, so that the same
+// group can be run with the hash of another implementation: Ristretto is
+// the original suite, RistrettoSha512 and RistrettoBlake2b the others. H is
+// part of the group parameters and is the same in every suite.
+use proofs::backend::Curve;
+
+use blake2::Blake2b;
+use curve25519_dalek::digest::generic_array::typenum::U64;
+use curve25519_dalek::digest::Digest;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, VartimeMultiscalarMul};
+use rand::{CryptoRng, RngCore};
+use sha2::Sha512;
+use sha3::Keccak512;
+
+use std::fmt;
+use std::marker::PhantomData;
+
+// The Ristretto group with H_p and H_s built on the 512-bit digest D
+pub struct Ristretto<D = Keccak512> {
+    _digest: PhantomData<fn() -> D>,
+}
+
+pub type RistrettoSha512 = Ristretto<Sha512>;
+pub type RistrettoBlake2b = Ristretto<Blake2b>;
+
+// written out, since deriving would require them of D as well
+impl<D> Clone for Ristretto<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for Ristretto<D> {}
+
+impl<D> Default for Ristretto<D> {
+    fn default() -> Self {
+        Ristretto { _digest: PhantomData }
+    }
+}
+
+impl<D> PartialEq for Ristretto<D> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<D> Eq for Ristretto<D> {}
+
+impl<D> fmt::Debug for Ristretto<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ristretto")
+    }
+}
+
+impl<D: Digest<OutputSize = U64> + Default> Curve for Ristretto<D> {
+    type Point = RistrettoPoint;
+
+    fn basepoint() -> RistrettoPoint {
+        constants::RISTRETTO_BASEPOINT_POINT
+    }
+
+    fn amount_generator() -> RistrettoPoint {
+        RistrettoPoint::hash_from_bytes::<Sha512>(constants::RISTRETTO_BASEPOINT_COMPRESSED.as_bytes())
+    }
+
+    fn identity() -> RistrettoPoint {
+        RistrettoPoint::identity()
+    }
+
+    fn random_point<R: RngCore + CryptoRng>(rng: &mut R) -> RistrettoPoint {
+        RistrettoPoint::random(rng)
+    }
+
+    fn compress(P: &RistrettoPoint) -> [u8; 32] {
+        P.compress().to_bytes()
+    }
+
+    fn decompress(bytes: &[u8]) -> Option<RistrettoPoint> {
+        if bytes.len() != 32 {
+            return None;
+        }
+        CompressedRistretto::from_slice(bytes).decompress()
+    }
+
+    fn hash_to_point(bytes: &[u8]) -> RistrettoPoint {
+        RistrettoPoint::hash_from_bytes::<D>(bytes)
+    }
+
+    fn hash_to_scalar(bytes: &[u8]) -> Scalar {
+        Scalar::hash_from_bytes::<D>(bytes)
+    }
+
+    fn in_prime_order_subgroup(_P: &RistrettoPoint) -> bool {
+        // every Ristretto element is
+        true
+    }
+
+    fn vartime_multiscalar_mul(scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+        RistrettoPoint::vartime_multiscalar_mul(scalars.iter(), points.iter())
+    }
+
+    fn vartime_double_scalar_mul_basepoint(a: &Scalar, A: &RistrettoPoint, b: &Scalar) -> RistrettoPoint {
+        RistrettoPoint::vartime_double_scalar_mul_basepoint(a, A, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::fixtures::Fixture;
+    use proofs::mprove::MProveProof;
+    use proofs::mprove_sigs::RingSig;
+
+    pub fn test_suite<D: Digest<OutputSize = U64> + Default>(){
+        let Fixture { stmt, witness } = Fixture::<Ristretto<D>>::generate(6, 2, 1).unwrap();
+        let proof = MProveProof::prove(&stmt, &witness).unwrap();
+        assert!(proof.verify(&stmt).is_ok());
+    }
+
+    #[test]
+    pub fn ciphersuites(){
+        test_suite::<Keccak512>();
+        test_suite::<Sha512>();
+        test_suite::<Blake2b>();
+
+        // the suites share the group but not the hashes
+        let bytes = b"MProve";
+        assert_ne!(<Ristretto>::hash_to_scalar(bytes), RistrettoSha512::hash_to_scalar(bytes));
+        assert_ne!(RistrettoSha512::hash_to_point(bytes), RistrettoBlake2b::hash_to_point(bytes));
+        assert_eq!(<Ristretto>::amount_generator(), RistrettoBlake2b::amount_generator());
+
+        // a signature made under one suite does not verify under another
+        let mut rng = rand::thread_rng();
+        let x = Scalar::random(&mut rng);
+        let pk = vec![<Ristretto>::basepoint() * x, <Ristretto>::random_point(&mut rng)];
+        let message = <Ristretto>::random_point(&mut rng);
+        let sig = RingSig::<Ristretto>::gen_RingSig(message, &pk, x, 0);
+        let other = RingSig::<RistrettoSha512>::read_from(&mut &sig.to_bytes()[..], 2).unwrap();
+        assert!(sig.ver_RingSig(message, &pk).is_ok());
+        assert!(other.ver_RingSig(message, &pk).is_err());
+    }
+}
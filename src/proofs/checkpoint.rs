@@ -0,0 +1,343 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Resumable MProve proving.
+//
+// A Checkpoint holds the positions proved so far, (C_prime_i, gamma_i,
+// sigma_i) for i < done, and the running sum of the z_i. Saved to disk, it
+// lets an interrupted prover carry on from position `done`. The file is
+//     magic | version (u32) | statement digest | witness digest |
+//     n (u32) | done (u32) | sum of z_i | entry_0 | ... | entry_{done-1} |
+//     SHA-256 of everything before it
+// with entries encoded as in the streaming layout. A checkpoint is only
+// resumed for the statement and witness it was made with.
+//
+// The checksum is unkeyed, so it only detects accidental corruption: anyone
+// who edits the file can recompute it. Entries read from disk are therefore
+// verified against the statement before proving carries on from them.
+//
+// The sum of the z_i is as secret as the blinding of C_res, so checkpoint
+// files must be protected like the wallet itself.
+use Errors::{self, MProveCheckpointError, MProveIoError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::mprove::{prove_position, verify_position, MProveProof, MProveStatement, MProveWitness};
+use proofs::mprove_sigs::{read_len, read_point, read_scalar, LSAGSig, RingSig};
+use proofs::opening::CResOpening;
+use proofs::transcript::Transcript;
+
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha256};
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"MProveCP";
+const VERSION: u32 = 1;
+
+#[derive(Clone)]
+pub struct Checkpoint<C: Curve = Ristretto> {
+    stmt_digest: [u8; 32],
+    witness_digest: [u8; 32],
+    n: usize,
+    z_sum: Scalar,
+    entries: Vec<(C::Point, RingSig<C>, LSAGSig<C>)>,
+    // entries known to verify; those read from disk are not, until checked
+    verified: usize,
+}
+
+impl<C: Curve> Checkpoint<C> {
+    // Empty checkpoint for proving `stmt` with `witness`
+    pub fn new(stmt: &MProveStatement<C>, witness: &MProveWitness) -> Result<Checkpoint<C>, Errors> {
+        stmt.check_generators()?;
+        witness.validate(stmt)?;
+        witness.total_amount()?;
+        Ok(Checkpoint {
+            stmt_digest: stmt.digest(),
+            witness_digest: witness_digest::<C>(witness),
+            n: stmt.len(),
+            z_sum: Scalar::zero(),
+            entries: Vec::new(),
+            verified: 0,
+        })
+    }
+
+    // number of positions proved
+    pub fn done(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.entries.len() == self.n
+    }
+
+    // Proves up to `count` more positions
+    pub fn advance(
+        &mut self,
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+        count: usize,
+    ) -> Result<(), Errors> {
+
+        self.check(stmt, witness)?;
+        self.verify_entries(stmt)?;
+        let message = stmt.message();
+        let mut rng = rand::thread_rng();
+
+        let start = self.done();
+        let end = std::cmp::min(self.n, start + count);
+        let mut owned = witness.owned().iter().filter(|out| out.index >= start).peekable();
+        for i in start..end {
+            let x = owned.next_if(|out| out.index == i).map(|out| out.x);
+            let z_i = Scalar::random(&mut rng);
+            self.entries.push(prove_position::<C, _>(message, stmt.G, stmt.C_vec[i], stmt.P_vec[i], z_i, x, &mut rng));
+            self.z_sum += z_i;
+        }
+        self.verified = self.entries.len();
+        Ok(())
+    }
+
+    // The proof and the opening of its C_res, once every position is proved
+    pub fn finish(
+        mut self,
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+    ) -> Result<(MProveProof<C>, CResOpening), Errors> {
+
+        self.check(stmt, witness)?;
+        self.verify_entries(stmt)?;
+        if !self.is_complete() {
+            return Err(MProveCheckpointError);
+        }
+
+        let terms: Vec<C::Point> = self
+            .entries
+            .iter()
+            .zip(stmt.C_vec.iter())
+            .map(|((C_prime_i, _, _), C_i)| *C_prime_i - *C_i)
+            .collect();
+        let opening = CResOpening {
+            amount: witness.total_amount()?,
+            blinding: self.z_sum - witness.owned().iter().map(|out| out.blinding).sum::<Scalar>(),
+        };
+        Ok((MProveProof::from_entries(self.entries, C::sum(&terms)), opening))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.stmt_digest);
+        bytes.extend_from_slice(&self.witness_digest);
+        bytes.extend_from_slice(&(self.n as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.z_sum.as_bytes());
+        for (C_prime_i, gamma, sigma) in self.entries.iter() {
+            bytes.extend_from_slice(&C::compress(C_prime_i));
+            bytes.extend_from_slice(&gamma.to_bytes());
+            bytes.extend_from_slice(&sigma.to_bytes());
+        }
+        let checksum = Sha256::digest(&bytes);
+        bytes.extend_from_slice(&checksum);
+        bytes
+    }
+
+    // Any damage to the encoding is MProveCheckpointError
+    pub fn from_bytes(bytes: &[u8]) -> Result<Checkpoint<C>, Errors> {
+        if bytes.len() < 32 {
+            return Err(MProveCheckpointError);
+        }
+        let (body, checksum) = bytes.split_at(bytes.len() - 32);
+        if Sha256::digest(body).as_slice() != checksum {
+            return Err(MProveCheckpointError);
+        }
+        Checkpoint::read_body(&mut &body[..]).map_err(|_| MProveCheckpointError)
+    }
+
+    // Writes the checkpoint through a temporary file, so an interruption
+    // while saving leaves the previous checkpoint in place
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Errors> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, self.to_bytes()).map_err(|_| MProveIoError)?;
+        fs::rename(&tmp, path).map_err(|_| MProveIoError)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Checkpoint<C>, Errors> {
+        let bytes = fs::read(path).map_err(|_| MProveIoError)?;
+        Checkpoint::from_bytes(&bytes)
+    }
+
+    fn read_body<R: Read>(reader: &mut R) -> Result<Checkpoint<C>, Errors> {
+        let mut magic = [0u8; 8];
+        let mut stmt_digest = [0u8; 32];
+        let mut witness_digest = [0u8; 32];
+        reader.read_exact(&mut magic).map_err(|_| MProveIoError)?;
+        if &magic != MAGIC || read_len(reader)? != VERSION as usize {
+            return Err(MProveCheckpointError);
+        }
+        reader.read_exact(&mut stmt_digest).map_err(|_| MProveIoError)?;
+        reader.read_exact(&mut witness_digest).map_err(|_| MProveIoError)?;
+        let n = read_len(reader)?;
+        let done = read_len(reader)?;
+        if done > n {
+            return Err(MProveCheckpointError);
+        }
+        let z_sum = read_scalar(reader)?;
+
+        let entries = (0..done)
+            .map(|_| {
+                Ok((
+                    read_point::<C, R>(reader)?,
+                    RingSig::read_from(reader, 2)?,
+                    LSAGSig::read_from(reader, 2)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, Errors>>()?;
+        if reader.read(&mut [0u8; 1]).map_err(|_| MProveIoError)? != 0 {
+            return Err(MProveCheckpointError);
+        }
+
+        Ok(Checkpoint {
+            stmt_digest,
+            witness_digest,
+            n,
+            z_sum,
+            entries,
+            verified: 0,
+        })
+    }
+
+    // the checkpoint belongs to this statement and witness
+    fn check(&self, stmt: &MProveStatement<C>, witness: &MProveWitness) -> Result<(), Errors> {
+        if self.stmt_digest != stmt.digest() || self.witness_digest != witness_digest::<C>(witness) {
+            return Err(MProveCheckpointError);
+        }
+        Ok(())
+    }
+
+    // verifies the entries not proved in this process, once
+    fn verify_entries(&mut self, stmt: &MProveStatement<C>) -> Result<(), Errors> {
+        let message = stmt.message();
+        for (i, (C_prime_i, gamma, sigma)) in self.entries.iter().enumerate().skip(self.verified) {
+            verify_position(message, *C_prime_i, *C_prime_i - stmt.C_vec[i], stmt.P_vec[i], gamma, sigma)
+                .map_err(|_| MProveCheckpointError)?;
+        }
+        self.verified = self.entries.len();
+        Ok(())
+    }
+}
+
+impl<C: Curve> MProveProof<C> {
+    // Same as prove_with_opening, saving a checkpoint to `path` every
+    // `interval` positions and resuming from it if it exists. The
+    // checkpoint is removed once the proof is complete.
+    pub fn prove_checkpointed<P: AsRef<Path>>(
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+        path: P,
+        interval: usize,
+    ) -> Result<(MProveProof<C>, CResOpening), Errors> {
+
+        stmt.check_generators()?;
+        let path = path.as_ref();
+        let mut checkpoint = if path.exists() {
+            Checkpoint::load(path)?
+        } else {
+            Checkpoint::new(stmt, witness)?
+        };
+
+        while !checkpoint.is_complete() {
+            checkpoint.advance(stmt, witness, std::cmp::max(interval, 1))?;
+            checkpoint.save(path)?;
+        }
+        let result = checkpoint.finish(stmt, witness)?;
+        fs::remove_file(path).map_err(|_| MProveIoError)?;
+        Ok(result)
+    }
+}
+
+// hash of the witness, kept in the checkpoint so that it is not resumed
+// with different secrets
+fn witness_digest<C: Curve>(witness: &MProveWitness) -> [u8; 32] {
+    let mut transcript = Transcript::<C>::new(b"MProve witness");
+    transcript.append_u64(b"owned", witness.owned().len() as u64);
+    for out in witness.owned().iter() {
+        transcript.append_u64(b"index", out.index as u64);
+        transcript.append_scalar(b"x", &out.x);
+        transcript.append_u64(b"amount", out.amount);
+        transcript.append_scalar(b"blinding", &out.blinding);
+    }
+    transcript.challenge_bytes(b"digest")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Errors::MProveError;
+    use proofs::backend::MoneroEd25519;
+    use proofs::fixtures::Fixture;
+
+    pub fn test_checkpoint<C: Curve>(name: &str){
+        let Fixture { stmt, witness } = Fixture::<C>::generate(10, 4, 5).unwrap();
+        let path = std::env::temp_dir().join(format!("mprove_checkpoint_{}_{}", name, std::process::id()));
+
+        // interrupted after 4 positions
+        let mut checkpoint = Checkpoint::new(&stmt, &witness).unwrap();
+        checkpoint.advance(&stmt, &witness, 4).unwrap();
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::<C>::load(&path).unwrap().done(), 4);
+
+        // resumed
+        let (proof, opening) = MProveProof::prove_checkpointed(&stmt, &witness, &path, 3).unwrap();
+        assert!(!path.exists());
+        assert!(proof.verify(&stmt).is_ok());
+        assert!(opening.verify(&stmt, &proof).is_ok());
+        assert_eq!(proof.to_bytes().len(), MProveProof::prove(&stmt, &witness).unwrap().to_bytes().len());
+
+        // corrupted, or for another statement or witness
+        let mut bytes = checkpoint.to_bytes();
+        bytes[100] ^= 1;
+        assert_eq!(Checkpoint::<C>::from_bytes(&bytes).err(), Some(MProveCheckpointError));
+        assert_eq!(Checkpoint::<C>::from_bytes(&bytes[..50]).err(), Some(MProveCheckpointError));
+        let other = Fixture::<C>::generate(10, 4, 6).unwrap();
+        let mut resumed = Checkpoint::<C>::from_bytes(&checkpoint.to_bytes()).unwrap();
+        assert_eq!(resumed.advance(&other.stmt, &other.witness, 1), Err(MProveCheckpointError));
+        assert_eq!(resumed.advance(&stmt, &other.witness, 1), Err(MProveCheckpointError));
+        assert_eq!(resumed.clone().finish(&stmt, &witness).err(), Some(MProveCheckpointError));
+        resumed.advance(&stmt, &witness, 100).unwrap();
+        assert!(resumed.finish(&stmt, &witness).unwrap().0.verify(&stmt).is_ok());
+
+        // edited entries pass a recomputed checksum, but not verification
+        let mut bytes = checkpoint.to_bytes();
+        let body = bytes.len() - 32;
+        let (start, size) = (116, (body - 116) / 4);
+        let first = bytes[start..start + size].to_vec();
+        bytes.copy_within(start + size..start + 2 * size, start);
+        bytes[start + size..start + 2 * size].copy_from_slice(&first);
+        let checksum = Sha256::digest(&bytes[..body]);
+        bytes[body..].copy_from_slice(&checksum);
+        let edited = Checkpoint::<C>::from_bytes(&bytes).unwrap();
+        assert_eq!(edited.clone().advance(&stmt, &witness, 1), Err(MProveCheckpointError));
+        assert_eq!(edited.finish(&stmt, &witness).err(), Some(MProveCheckpointError));
+
+        // generators that are not those of the named set
+        let mislabelled = MProveStatement { generators: Some(b"audit".to_vec()), ..stmt.clone() };
+        assert_eq!(Checkpoint::new(&mislabelled, &witness).err(), Some(MProveError));
+        assert_eq!(MProveProof::prove_checkpointed(&mislabelled, &witness, &path, 3).err(), Some(MProveError));
+    }
+
+    #[test]
+    pub fn resume_from_checkpoint(){
+        test_checkpoint::<Ristretto>("ristretto");
+        test_checkpoint::<MoneroEd25519>("monero");
+    }
+}
@@ -0,0 +1,98 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Reading the exports the library takes as input.
+//
+// An export is CSV with a fixed header, or a JSON array of objects with
+// the same fields, and is read as JSON if the file name ends in .json and
+// as CSV otherwise. Keys and scalars in it are hex. The output export of
+// anon_set and the wallet export of wallet are both read through here, each
+// with its own ExportRecord.
+use Errors::{self, MProveImportError};
+use proofs::backend::Curve;
+
+use curve25519_dalek::scalar::Scalar;
+use serde::de::DeserializeOwned;
+
+use std::fs;
+use std::path::Path;
+
+// Raw row of an export, before its keys are decoded
+pub trait ExportRecord: DeserializeOwned + Sized {
+    // CSV header, which is also the JSON field names
+    const HEADER: &'static [&'static str];
+
+    // the record from the fields of a CSV row, in header order
+    fn from_fields(fields: &[&str]) -> Result<Self, Errors>;
+}
+
+pub fn load_records<T: ExportRecord, P: AsRef<Path>>(path: P) -> Result<Vec<T>, Errors> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|_| MProveImportError)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => json_records(&text),
+        _ => csv_records(&text),
+    }
+}
+
+pub fn json_records<T: ExportRecord>(text: &str) -> Result<Vec<T>, Errors> {
+    serde_json::from_str(text).map_err(|_| MProveImportError)
+}
+
+pub fn csv_records<T: ExportRecord>(text: &str) -> Result<Vec<T>, Errors> {
+    csv_rows(text, T::HEADER)?
+        .iter()
+        .map(|fields| T::from_fields(fields))
+        .collect()
+}
+
+// Rows of a CSV export with the given header. Blank lines and lines
+// starting with '#' are skipped, and every row must have as many fields as
+// the header.
+pub fn csv_rows<'a>(text: &'a str, header: &[&str]) -> Result<Vec<Vec<&'a str>>, Errors> {
+    let mut rows = text
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split(',').map(|field| field.trim()).collect::<Vec<&str>>());
+
+    if rows.next().as_ref().map(|row| &row[..]) != Some(header) {
+        return Err(MProveImportError);
+    }
+    rows.map(|row| if row.len() == header.len() { Ok(row) } else { Err(MProveImportError) })
+        .collect()
+}
+
+// 32 bytes of hex
+fn decode_bytes(hex_str: &str) -> Result<[u8; 32], Errors> {
+    let bytes = hex::decode(hex_str.trim()).map_err(|_| MProveImportError)?;
+    if bytes.len() != 32 {
+        return Err(MProveImportError);
+    }
+    let mut buf = [0u8; 32];
+    buf.copy_from_slice(&bytes);
+    Ok(buf)
+}
+
+pub fn decode_point<C: Curve>(hex_str: &str) -> Result<C::Point, Errors> {
+    decode_key::<C>(hex_str)?.ok_or(MProveImportError)
+}
+
+// Key of an export row: None if it is 32 bytes that are not a point, and
+// MProveImportError if it is not 32 bytes of hex at all
+pub fn decode_key<C: Curve>(hex_str: &str) -> Result<Option<C::Point>, Errors> {
+    Ok(C::decompress(&decode_bytes(hex_str)?))
+}
+
+// canonical little-endian scalar
+pub fn decode_scalar(hex_str: &str) -> Result<Scalar, Errors> {
+    Scalar::from_canonical_bytes(decode_bytes(hex_str)?).ok_or(MProveImportError)
+}
@@ -0,0 +1,123 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Random MProve statements and witnesses for tests, benches and the
+// simulator. Everything is drawn from a ChaCha20 stream seeded by the
+// caller, so a (n, s, seed) triple always gives the same fixture.
+use Errors::{self, MProveError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::generators::Generators;
+use proofs::mprove::{MProveStatement, MProveWitness, OwnedOutput};
+
+use curve25519_dalek::scalar::Scalar;
+
+use rand::seq::index;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+#[derive(Clone)]
+pub struct Fixture<C: Curve = Ristretto> {
+    pub stmt: MProveStatement<C>,
+    pub witness: MProveWitness,
+}
+
+impl<C: Curve> Fixture<C> {
+    // Anonymity set of n outputs, s of them owned by the exchange at
+    // uniformly random positions, with 32-bit random amounts. Any
+    // 0 <= s <= n with n >= 1 is accepted.
+    pub fn generate(n: usize, s: usize, seed: u64) -> Result<Fixture<C>, Errors> {
+        if n == 0 || s > n {
+            return Err(MProveError);
+        }
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+
+        let generators = Generators::<C>::standard();
+        let (G, H) = (generators.G(), generators.H());
+
+        let mut P_vec: Vec<C::Point> = (0..n).map(|_| C::random_point(&mut rng)).collect();
+        let mut C_vec: Vec<C::Point> = (0..n).map(|_| C::random_point(&mut rng)).collect();
+
+        // owned positions, sorted as the witness requires
+        let mut idx = index::sample(&mut rng, n, s).into_vec();
+        idx.sort_unstable();
+
+        let owned: Vec<OwnedOutput> = idx
+            .into_iter()
+            .map(|i| {
+                let x = Scalar::random(&mut rng);
+                let amount = u64::from(rng.gen::<u32>());
+                let blinding = Scalar::random(&mut rng);
+                P_vec[i] = G * x;
+                C_vec[i] = G * blinding + H * Scalar::from(amount);
+                OwnedOutput {
+                    index: i,
+                    x,
+                    amount,
+                    blinding,
+                }
+            })
+            .collect();
+
+        Ok(Fixture {
+            stmt: MProveStatement::from_generators(&generators, C_vec, P_vec, b"")?,
+            witness: MProveWitness::new(owned)?,
+        })
+    }
+
+    // amounts of the owned outputs, in position order
+    pub fn amounts(&self) -> Vec<u64> {
+        self.witness.owned().iter().map(|out| out.amount).collect()
+    }
+
+    // blinding factors of the owned outputs, in position order
+    pub fn blindings(&self) -> Vec<Scalar> {
+        self.witness.owned().iter().map(|out| out.blinding).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::backend::MoneroEd25519;
+    use proofs::mprove::MProveProof;
+
+    pub fn test_fixture<C: Curve>(n: usize, s: usize){
+        let fixture = Fixture::<C>::generate(n, s, 42).unwrap();
+        assert_eq!(fixture.stmt.len(), n);
+        assert_eq!(fixture.witness.owned().len(), s);
+        assert_eq!(fixture.amounts().len(), s);
+        assert_eq!(fixture.blindings().len(), s);
+        assert!(fixture.witness.validate(&fixture.stmt).is_ok());
+
+        let proof = MProveProof::prove(&fixture.stmt, &fixture.witness).unwrap();
+        assert!(proof.verify(&fixture.stmt).is_ok());
+    }
+
+    #[test]
+    pub fn fixture_edge_cases(){
+        for &(n, s) in [(1, 0), (1, 1), (2, 1), (2, 2), (5, 0), (5, 1), (5, 3), (5, 5), (7, 6)].iter() {
+            test_fixture::<Ristretto>(n, s);
+        }
+        test_fixture::<MoneroEd25519>(4, 4);
+
+        assert_eq!(Fixture::<Ristretto>::generate(3, 4, 0).err(), Some(MProveError));
+        assert_eq!(Fixture::<Ristretto>::generate(0, 0, 0).err(), Some(MProveError));
+
+        // same seed, same fixture
+        let a = Fixture::<Ristretto>::generate(6, 2, 7).unwrap();
+        let b = Fixture::<Ristretto>::generate(6, 2, 7).unwrap();
+        assert_eq!(a.stmt, b.stmt);
+        assert_eq!(a.amounts(), b.amounts());
+        assert_eq!(a.blindings(), b.blindings());
+        let c = Fixture::<Ristretto>::generate(6, 2, 8).unwrap();
+        assert!(a.stmt != c.stmt);
+    }
+}
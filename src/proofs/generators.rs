@@ -0,0 +1,134 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Nothing-up-my-sleeve generators.
+//
+// A generator set is known by its name. G is always the backend's
+// basepoint, and every other generator is hashed to a point from the name
+// and its own label, so anyone can recompute a set from its name and no
+// discrete log relation between its generators is known:
+//     H             amounts,
+//     asset(label)  the amount generator of an asset,
+//     vector(l, n)  n generators labelled l, e.g. for range proofs.
+// The set named "standard" is the one the chain commits amounts with: its H
+// is the backend's amount generator, itself derived from the basepoint.
+use Errors::{self, MProveError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::transcript::Transcript;
+
+pub const STANDARD: &[u8] = b"standard";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Generators<C: Curve = Ristretto> {
+    name: Vec<u8>,
+    G: C::Point,
+    H: C::Point,
+}
+
+impl<C: Curve> Generators<C> {
+    // The chain's own generators
+    pub fn standard() -> Generators<C> {
+        Generators {
+            name: STANDARD.to_vec(),
+            G: C::basepoint(),
+            H: C::amount_generator(),
+        }
+    }
+
+    // The set called `name`
+    pub fn from_name(name: &[u8]) -> Generators<C> {
+        if name == STANDARD {
+            return Generators::standard();
+        }
+        Generators {
+            name: name.to_vec(),
+            G: C::basepoint(),
+            H: derive::<C>(name, b"H", 0),
+        }
+    }
+
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    pub fn G(&self) -> C::Point {
+        self.G
+    }
+
+    pub fn H(&self) -> C::Point {
+        self.H
+    }
+
+    // amount generator of the asset `label`
+    pub fn asset(&self, label: &[u8]) -> C::Point {
+        let mut name = b"asset:".to_vec();
+        name.extend_from_slice(label);
+        derive::<C>(&self.name, &name, 0)
+    }
+
+    // n generators labelled `label`
+    pub fn vector(&self, label: &[u8], n: usize) -> Vec<C::Point> {
+        let mut name = b"vector:".to_vec();
+        name.extend_from_slice(label);
+        (0..n).map(|i| derive::<C>(&self.name, &name, i as u64)).collect()
+    }
+
+    // Checks that G and H are those of this set
+    pub fn check(&self, G: &C::Point, H: &C::Point) -> Result<(), Errors> {
+        if *G == self.G && *H == self.H {
+            Ok(())
+        } else {
+            Err(MProveError)
+        }
+    }
+}
+
+// generator `label`, number `index`, of the set `name`
+fn derive<C: Curve>(name: &[u8], label: &[u8], index: u64) -> C::Point {
+    let mut transcript = Transcript::<C>::new(b"MProve generators");
+    transcript.append_message(b"set", name);
+    transcript.append_message(b"label", label);
+    transcript.append_u64(b"index", index);
+    transcript.challenge_point(b"generator")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::backend::MoneroEd25519;
+    use std::collections::HashSet;
+
+    pub fn test_generators<C: Curve>(){
+        let standard = Generators::<C>::standard();
+        assert_eq!(standard, Generators::from_name(STANDARD));
+        assert_eq!(standard.H(), C::amount_generator());
+
+        // reproducible, and distinct across sets and labels
+        let audit = Generators::<C>::from_name(b"audit 2020");
+        assert_eq!(audit, Generators::from_name(b"audit 2020"));
+        assert_eq!(audit.G(), standard.G());
+        let mut points = vec![standard.G(), standard.H(), audit.H(), audit.asset(b"XMR"), standard.asset(b"XMR")];
+        points.extend(audit.vector(b"G", 8));
+        points.extend(audit.vector(b"H", 8));
+        let distinct: HashSet<[u8; 32]> = points.iter().map(|P| C::compress(P)).collect();
+        assert_eq!(distinct.len(), points.len());
+        assert_eq!(audit.vector(b"G", 8)[..4], audit.vector(b"G", 4)[..]);
+
+        assert!(audit.check(&audit.G(), &audit.H()).is_ok());
+        assert_eq!(audit.check(&standard.G(), &standard.H()), Err(MProveError));
+    }
+
+    #[test]
+    pub fn derive_generators(){
+        test_generators::<Ristretto>();
+        test_generators::<MoneroEd25519>();
+    }
+}
@@ -0,0 +1,375 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Incremental MProve between consecutive audits.
+//
+// C_res is the sum of one term C_prime_i - C_i per position, and the LSAG
+// at position i carries a key image, which for an owned output is the key
+// image the output is spent with. A verifier that keeps (key image, term)
+// for every position can therefore update C_res when the set grows or
+// owned outputs are spent, without re-running the whole proof:
+//     - new outputs are covered by an ordinary MProve proof (the delta
+//       proof) over just those outputs, whose C_res is added, and
+//     - every kept position whose key image has since appeared on chain is
+//       an owned output that was spent, and its term is subtracted. This
+//       includes new outputs that were spent before the delta audit.
+// The delta proof is bound to the state it extends through its context.
+// The key images of spent outputs are kept for good, so an output listed
+// again in a later delta cannot be counted a second time, and the verifier
+// builds the delta statement from the outputs it sees on chain rather than
+// taking one from the prover.
+use Errors::{self, MProveError, MProveOpeningError, MProveWitnessError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::mprove::{MProveProof, MProveStatement, MProveWitness};
+use proofs::opening::CResOpening;
+use proofs::transcript::Transcript;
+
+use curve25519_dalek::scalar::Scalar;
+
+use std::collections::HashSet;
+
+// A position of an audited anonymity set: its key image and C_prime_i - C_i
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEntry<C: Curve = Ristretto> {
+    pub key_image: C::Point,
+    pub term: C::Point,
+}
+
+// What a verifier keeps after checking an audit: the generators, the
+// current C_res, the unspent positions of every audit so far and the key
+// images of the positions taken out as spent
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditState<C: Curve = Ristretto> {
+    G: C::Point,
+    H: C::Point,
+    generators: Option<Vec<u8>>,
+    C_res: C::Point,
+    entries: Vec<AuditEntry<C>>,
+    retired: Vec<C::Point>,
+}
+
+impl<C: Curve> AuditState<C> {
+    // State after a full audit, once its proof verifies
+    pub fn initial(stmt: &MProveStatement<C>, proof: &MProveProof<C>) -> Result<AuditState<C>, Errors> {
+        proof.verify(stmt)?;
+        Ok(AuditState {
+            G: stmt.G,
+            H: stmt.H,
+            generators: stmt.generators.clone(),
+            C_res: proof.C_res(),
+            entries: AuditState::entries(stmt, proof),
+            retired: Vec::new(),
+        })
+    }
+
+    // Statement for the outputs added since this state. Its context commits
+    // to the state, so the delta proof cannot be chained onto another one.
+    pub fn delta_statement(
+        &self,
+        C_vec: Vec<C::Point>,
+        P_vec: Vec<C::Point>,
+    ) -> Result<MProveStatement<C>, Errors> {
+        let stmt = MProveStatement::new(self.G, self.H, C_vec, P_vec, &self.digest())?;
+        Ok(MProveStatement {
+            generators: self.generators.clone(),
+            ..stmt
+        })
+    }
+
+    // State after the outputs (C_vec, P_vec) were added, and the outputs
+    // whose key images are in `spent` were spent. The outputs and `spent`
+    // must come from the chain, not from the prover.
+    pub fn apply(
+        &self,
+        C_vec: Vec<C::Point>,
+        P_vec: Vec<C::Point>,
+        delta_proof: &MProveProof<C>,
+        spent: &[C::Point],
+    ) -> Result<AuditState<C>, Errors> {
+
+        let delta_stmt = self.delta_statement(C_vec, P_vec)?;
+        delta_proof.verify(&delta_stmt)?;
+
+        let spent: HashSet<[u8; 32]> = spent.iter().map(|I| C::compress(I)).collect();
+        let (removed, mut entries): (Vec<AuditEntry<C>>, Vec<AuditEntry<C>>) = self
+            .entries
+            .iter()
+            .cloned()
+            .partition(|entry| spent.contains(&C::compress(&entry.key_image)));
+
+        // a key image may only be counted once, ever, and a new output that
+        // was already spent is taken out again
+        let mut retired = self.retired.clone();
+        retired.extend(removed.iter().map(|entry| entry.key_image));
+        let mut removed_terms: Vec<C::Point> = removed.iter().map(|entry| entry.term).collect();
+        let mut seen: HashSet<[u8; 32]> = entries
+            .iter()
+            .map(|entry| &entry.key_image)
+            .chain(self.retired.iter())
+            .map(|I| C::compress(I))
+            .collect();
+        for entry in AuditState::entries(&delta_stmt, delta_proof) {
+            let key_image = C::compress(&entry.key_image);
+            if !seen.insert(key_image) {
+                return Err(MProveError);
+            }
+            if spent.contains(&key_image) {
+                retired.push(entry.key_image);
+                removed_terms.push(entry.term);
+            } else {
+                entries.push(entry);
+            }
+        }
+
+        Ok(AuditState {
+            G: self.G,
+            H: self.H,
+            generators: self.generators.clone(),
+            C_res: self.C_res - C::sum(&removed_terms) + delta_proof.C_res(),
+            entries,
+            retired,
+        })
+    }
+
+    pub fn C_res(&self) -> C::Point {
+        self.C_res
+    }
+
+    // number of unspent positions kept
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Full opening of the current C_res
+    pub fn verify_opening(&self, opening: &CResOpening) -> Result<(), Errors> {
+        if self.C_res + self.H * Scalar::from(opening.amount) == self.G * opening.blinding {
+            Ok(())
+        } else {
+            Err(MProveOpeningError)
+        }
+    }
+
+    // hash of everything the state holds
+    pub fn digest(&self) -> Vec<u8> {
+        let mut transcript = Transcript::<C>::new(b"MProve audit state");
+        transcript.append_point(b"G", &self.G);
+        transcript.append_point(b"H", &self.H);
+        match self.generators {
+            Some(ref name) => transcript.append_message(b"generators", name),
+            None => transcript.append_message(b"no generators", b""),
+        }
+        transcript.append_point(b"C_res", &self.C_res);
+        transcript.append_u64(b"entries", self.entries.len() as u64);
+        for entry in self.entries.iter() {
+            transcript.append_point(b"key image", &entry.key_image);
+            transcript.append_point(b"term", &entry.term);
+        }
+        transcript.append_points(b"retired", &self.retired);
+        transcript.challenge_bytes(b"digest").to_vec()
+    }
+
+    fn entries(stmt: &MProveStatement<C>, proof: &MProveProof<C>) -> Vec<AuditEntry<C>> {
+        proof
+            .key_images()
+            .into_iter()
+            .zip(proof.C_prime_vec().iter().zip(stmt.C_vec.iter()))
+            .map(|(key_image, (C_prime_i, C_i))| AuditEntry { key_image, term: *C_prime_i - *C_i })
+            .collect()
+    }
+}
+
+// The prover's side: the public state, the opening of its C_res and the
+// opening of the term of every unspent owned output
+#[derive(Clone)]
+pub struct IncrementalProver<C: Curve = Ristretto> {
+    state: AuditState<C>,
+    opening: CResOpening,
+    owned: Vec<(C::Point, CResOpening)>,
+}
+
+impl<C: Curve> IncrementalProver<C> {
+    // Full audit of `stmt`, and the prover state it leaves
+    pub fn start(
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+    ) -> Result<(MProveProof<C>, IncrementalProver<C>), Errors> {
+
+        let (proof, opening, output_openings) = MProveProof::prove_with_output_openings(stmt, witness)?;
+        let state = AuditState::initial(stmt, &proof)?;
+        let prover = IncrementalProver {
+            state,
+            opening,
+            owned: IncrementalProver::owned(stmt, witness, output_openings),
+        };
+        Ok((proof, prover))
+    }
+
+    // Delta proof for the outputs (C_vec, P_vec) added since the last
+    // audit, with the owned ones given by `witness` (positions relative to
+    // the new outputs), after the outputs with key images `spent` were
+    // spent. Moves the prover to the new state.
+    pub fn prove_delta(
+        &mut self,
+        C_vec: Vec<C::Point>,
+        P_vec: Vec<C::Point>,
+        witness: &MProveWitness,
+        spent: &[C::Point],
+    ) -> Result<MProveProof<C>, Errors> {
+
+        let delta_stmt = self.state.delta_statement(C_vec, P_vec)?;
+        let (delta_proof, delta_opening, output_openings) =
+            MProveProof::prove_with_output_openings(&delta_stmt, witness)?;
+        let state = self.state.apply(delta_stmt.C_vec.clone(), delta_stmt.P_vec.clone(), &delta_proof, spent)?;
+
+        // take the spent outputs out of the opening, including new ones
+        // that were spent before this audit
+        let spent: HashSet<[u8; 32]> = spent.iter().map(|I| C::compress(I)).collect();
+        let (removed, owned): (Vec<_>, Vec<_>) = self
+            .owned
+            .iter()
+            .cloned()
+            .chain(IncrementalProver::owned(&delta_stmt, witness, output_openings))
+            .partition(|(I, _)| spent.contains(&C::compress(I)));
+
+        let mut opening = delta_opening;
+        opening.amount = self
+            .opening
+            .amount
+            .checked_add(opening.amount)
+            .ok_or(MProveWitnessError)?;
+        opening.blinding += self.opening.blinding;
+        for (_, out) in removed.iter() {
+            opening.amount -= out.amount;
+            opening.blinding -= out.blinding;
+        }
+
+        self.state = state;
+        self.opening = opening;
+        self.owned = owned;
+        Ok(delta_proof)
+    }
+
+    pub fn state(&self) -> &AuditState<C> {
+        &self.state
+    }
+
+    // opening of the current C_res; secret unless it is to be revealed
+    pub fn opening(&self) -> &CResOpening {
+        &self.opening
+    }
+
+    // key image of every owned output, paired with the opening of its term
+    fn owned(
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+        output_openings: Vec<CResOpening>,
+    ) -> Vec<(C::Point, CResOpening)> {
+        witness
+            .owned()
+            .iter()
+            .zip(output_openings)
+            .map(|(out, opening)| (C::key_image_base(&stmt.P_vec[out.index]) * out.x, opening))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Errors::MProveSigsError;
+    use proofs::backend::MoneroEd25519;
+    use proofs::fixtures::Fixture;
+    use proofs::mprove::OwnedOutput;
+
+    pub fn test_incremental<C: Curve>(){
+        let Fixture { stmt, witness } = Fixture::<C>::generate(8, 3, 0).unwrap();
+        let (proof, mut prover) = IncrementalProver::start(&stmt, &witness).unwrap();
+
+        // the verifier checks the full audit once
+        let state = AuditState::initial(&stmt, &proof).unwrap();
+        assert_eq!(&state, prover.state());
+        assert!(state.verify_opening(prover.opening()).is_ok());
+
+        // 5 new outputs, 2 owned, and the first owned output spent
+        let new = Fixture::<C>::generate(5, 2, 1).unwrap();
+        let first = &witness.owned()[0];
+        let spent = vec![
+            C::key_image_base(&stmt.P_vec[first.index]) * first.x,
+            C::random_point(&mut rand::thread_rng()),
+        ];
+        let (C_vec, P_vec) = (new.stmt.C_vec.clone(), new.stmt.P_vec.clone());
+        let delta_proof = prover.prove_delta(C_vec.clone(), P_vec.clone(), &new.witness, &spent).unwrap();
+
+        let state = state.apply(C_vec.clone(), P_vec.clone(), &delta_proof, &spent).unwrap();
+        assert_eq!(&state, prover.state());
+        assert_eq!(state.len(), 8 + 5 - 1);
+        assert!(state.verify_opening(prover.opening()).is_ok());
+        let expected = witness.total_amount().unwrap() - first.amount + new.witness.total_amount().unwrap();
+        assert_eq!(prover.opening().amount, expected);
+
+        // the delta proof only chains onto the state it was made for
+        let initial = AuditState::initial(&stmt, &proof).unwrap();
+        assert_eq!(state.apply(C_vec.clone(), P_vec.clone(), &delta_proof, &[]), Err(MProveSigsError));
+        assert!(initial.apply(C_vec.clone(), P_vec.clone(), &delta_proof, &spent).is_ok());
+
+        // and to the outputs the verifier sees
+        let mut other_P_vec = P_vec.clone();
+        other_P_vec.swap(0, 1);
+        assert!(initial.apply(C_vec.clone(), other_P_vec, &delta_proof, &spent).is_err());
+
+        // the prover's opening accounts for the spent output
+        let ignored = initial.apply(C_vec, P_vec, &delta_proof, &[]).unwrap();
+        assert_eq!(ignored.verify_opening(prover.opening()), Err(MProveOpeningError));
+
+        // a spent output listed again as new is not counted again
+        let mut relisted = Fixture::<C>::generate(3, 0, 3).unwrap().stmt;
+        relisted.C_vec[1] = stmt.C_vec[first.index];
+        relisted.P_vec[1] = stmt.P_vec[first.index];
+        let again = MProveWitness::new(vec![OwnedOutput { index: 1, ..first.clone() }]).unwrap();
+        let relisted_stmt = state.delta_statement(relisted.C_vec.clone(), relisted.P_vec.clone()).unwrap();
+        let relisted_proof = MProveProof::prove(&relisted_stmt, &again).unwrap();
+        assert_eq!(state.apply(relisted.C_vec.clone(), relisted.P_vec.clone(), &relisted_proof, &[]), Err(MProveError));
+        let before = prover.state().clone();
+        assert_eq!(prover.prove_delta(relisted.C_vec, relisted.P_vec, &again, &[]).err(), Some(MProveError));
+        assert_eq!(prover.state(), &before);
+
+        // a new owned output that is already spent is not counted
+        let newer = Fixture::<C>::generate(4, 2, 2).unwrap();
+        let fresh = &newer.witness.owned()[0];
+        let spent = vec![C::key_image_base(&newer.stmt.P_vec[fresh.index]) * fresh.x];
+        let (C_vec, P_vec) = (newer.stmt.C_vec.clone(), newer.stmt.P_vec.clone());
+        let newer_proof = prover.prove_delta(C_vec.clone(), P_vec.clone(), &newer.witness, &spent).unwrap();
+        let state = state.apply(C_vec, P_vec, &newer_proof, &spent).unwrap();
+        assert_eq!(&state, prover.state());
+        assert_eq!(state.len(), 8 + 5 - 1 + 4 - 1);
+        assert!(state.verify_opening(prover.opening()).is_ok());
+        let expected = expected + newer.witness.total_amount().unwrap() - fresh.amount;
+        assert_eq!(prover.opening().amount, expected);
+
+        // the digest tells unnamed generators from any name
+        let unnamed = AuditState { generators: None, ..state.clone() };
+        let empty = AuditState { generators: Some(Vec::new()), ..state.clone() };
+        assert!(unnamed.digest() != state.digest());
+        assert!(unnamed.digest() != empty.digest());
+        let forgetful = AuditState { retired: Vec::new(), ..state.clone() };
+        assert!(forgetful.digest() != state.digest());
+    }
+
+    #[test]
+    pub fn sim_incremental(){
+        test_incremental::<Ristretto>();
+        test_incremental::<MoneroEd25519>();
+    }
+}
@@ -15,5 +15,22 @@ version 3 of the License, or (at your option) any later version.
 
 // based on the paper: https://eprint.iacr.org/2017/1066.pdf
 
+pub mod backend;
+pub mod transcript;
+pub mod generators;
 pub mod mprove_sigs;
 pub mod mprove;
+pub mod opening;
+pub mod export;
+pub mod anon_set;
+pub mod wallet;
+pub mod sampler;
+pub mod fixtures;
+pub mod multi_asset;
+pub mod incremental;
+pub mod streaming;
+pub mod checkpoint;
+pub mod threshold;
+pub mod vectors;
+pub mod schnorr;
+pub mod report;
@@ -10,312 +10,677 @@ This file is part of MProvelibrary
 */
 
 // based on the paper: <link to paper>
-use Errors::{self, MProveError};
+use Errors::{self, MProveError, MProveIdentityError, MProveWitnessError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::generators::{Generators, STANDARD};
 use proofs::mprove_sigs::*;
+use proofs::opening::CResOpening;
+use proofs::schnorr::{SchnorrSig, SCHNORR_BYTES};
+use proofs::transcript::Transcript;
 
-use curve25519_dalek::ristretto::{RistrettoPoint};
-use curve25519_dalek::traits::VartimeMultiscalarMul;
 use curve25519_dalek::scalar::Scalar;
+use rand::{CryptoRng, RngCore};
+
+use std::collections::HashSet;
+
+// Public statement: the generators G and H (amounts) and the name of the
+// generator set they come from, if any, the anonymity set of commitments
+// C_vec and one-time addresses P_vec, a context string the proof is bound
+// to, and the identity key of the exchange claiming it, if any
+#[derive(Clone, Debug, PartialEq)]
+pub struct MProveStatement<C: Curve = Ristretto> {
+    pub G: C::Point,
+    pub H: C::Point,
+    pub generators: Option<Vec<u8>>,
+    pub C_vec: Vec<C::Point>,
+    pub P_vec: Vec<C::Point>,
+    pub context: Vec<u8>,
+    pub identity: Option<C::Point>,
+}
+
+impl<C: Curve> MProveStatement<C> {
+    pub fn new(
+        G: C::Point,
+        H: C::Point,
+        C_vec: Vec<C::Point>,
+        P_vec: Vec<C::Point>,
+        context: &[u8],
+    ) -> Result<MProveStatement<C>, Errors> {
+
+        if C_vec.len() != P_vec.len() || P_vec.is_empty() {
+            return Err(MProveError);
+        }
+
+        // an output listed twice could be counted twice
+        let distinct: HashSet<[u8; 32]> = P_vec.iter().map(|P| C::compress(P)).collect();
+        if distinct.len() != P_vec.len() {
+            return Err(MProveError);
+        }
+
+        // the standard set is recognised, any other G and H are unnamed
+        let generators = match Generators::<C>::standard().check(&G, &H) {
+            Ok(()) => Some(STANDARD.to_vec()),
+            Err(_) => None,
+        };
+
+        Ok(MProveStatement {
+            G,
+            H,
+            generators,
+            C_vec,
+            P_vec,
+            context: context.to_vec(),
+            identity: None,
+        })
+    }
+
+    // Statement over the G and H of a named generator set
+    pub fn from_generators(
+        generators: &Generators<C>,
+        C_vec: Vec<C::Point>,
+        P_vec: Vec<C::Point>,
+        context: &[u8],
+    ) -> Result<MProveStatement<C>, Errors> {
+        let stmt = MProveStatement::new(generators.G(), generators.H(), C_vec, P_vec, context)?;
+        Ok(MProveStatement {
+            generators: Some(generators.name().to_vec()),
+            ..stmt
+        })
+    }
+
+    // The same statement, claimed by the exchange with identity key P. Its
+    // proofs commit to P in every ring signature, so a claim cannot be
+    // moved to another key after proving.
+    pub fn claimed_by(&self, P: C::Point) -> MProveStatement<C> {
+        MProveStatement {
+            identity: Some(P),
+            ..self.clone()
+        }
+    }
+
+    // G and H are those of the named generator set, if there is one
+    pub fn check_generators(&self) -> Result<(), Errors> {
+        match self.generators {
+            Some(ref name) => Generators::<C>::from_name(name).check(&self.G, &self.H),
+            None => Ok(()),
+        }
+    }
+
+    // anonymity set size
+    pub fn len(&self) -> usize {
+        self.P_vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.P_vec.is_empty()
+    }
+
+    // Message signed by every ring signature in the proof. It binds the
+    // proof to G, H, the generator set, the set size, the context and the
+    // identity key; the rings themselves bind each C_i and P_i.
+    pub fn message(&self) -> C::Point {
+        statement_message::<C>(
+            &self.G,
+            &self.H,
+            self.generators.as_ref().map(|name| &name[..]),
+            self.len(),
+            &self.context,
+            self.identity.as_ref(),
+        )
+    }
+
+    // Hash of the whole statement, outputs included
+    pub fn digest(&self) -> [u8; 32] {
+        let mut transcript = Transcript::<C>::new(b"MProve statement");
+        transcript.append_point(b"message", &self.message());
+        transcript.append_points(b"C", &self.C_vec);
+        transcript.append_points(b"P", &self.P_vec);
+        transcript.challenge_bytes(b"digest")
+    }
+}
+
+// The message of a statement, from its size alone, so that it can be
+// computed before the anonymity set is read
+pub fn statement_message<C: Curve>(
+    G: &C::Point,
+    H: &C::Point,
+    generators: Option<&[u8]>,
+    n: usize,
+    context: &[u8],
+    identity: Option<&C::Point>,
+) -> C::Point {
+    let mut transcript = Transcript::<C>::new(b"MProve");
+    transcript.append_point(b"G", G);
+    transcript.append_point(b"H", H);
+    match generators {
+        Some(name) => transcript.append_message(b"generators", name),
+        None => transcript.append_message(b"no generators", b""),
+    }
+    transcript.append_u64(b"n", n as u64);
+    transcript.append_message(b"context", context);
+    match identity {
+        Some(P) => transcript.append_point(b"identity", P),
+        None => transcript.append_message(b"no identity", b""),
+    }
+    transcript.challenge_point(b"message")
+}
+
+// C_prime_i and the two ring signatures of position i. If the exchange owns
+// the output, x is its secret key, C_prime_i = z_i*G and the signatures use
+// the first ring members; otherwise C_prime_i - C_i = z_i*G and they use the
+// second.
+pub(crate) fn prove_position<C: Curve, R: RngCore + CryptoRng>(
+    message: C::Point,
+    G: C::Point,
+    C_i: C::Point,
+    P_i: C::Point,
+    z_i: Scalar,
+    x: Option<Scalar>,
+    rng: &mut R,
+) -> (C::Point, RingSig<C>, LSAGSig<C>) {
+
+    let C_prime_i = match x {
+        Some(_) => G * z_i,
+        None => G * z_i + C_i,
+    };
+
+    // construct pk vectors
+    let C_primei_Ci = C_prime_i - C_i;
+    let pk_gamma = vec![C_prime_i, C_primei_Ci];
+    let pk_sigma = vec![P_i, C_primei_Ci];
+
+    // gen ring signatures
+    let (gamma, sigma) = match x {
+        Some(x) => (
+            RingSig::gen_RingSig_with_rng(message, &pk_gamma, z_i, 0, rng),
+            LSAGSig::gen_LSAG_with_rng(message, &pk_sigma, x, 0, rng),
+        ),
+        None => (
+            RingSig::gen_RingSig_with_rng(message, &pk_gamma, z_i, 1, rng),
+            LSAGSig::gen_LSAG_with_rng(message, &pk_sigma, z_i, 1, rng),
+        ),
+    };
+    (C_prime_i, gamma, sigma)
+}
+
+// Checks the ring signatures of position i, given C_prime_i - C_i
+pub(crate) fn verify_position<C: Curve>(
+    message: C::Point,
+    C_prime_i: C::Point,
+    C_sub_i: C::Point,
+    P_i: C::Point,
+    gamma: &RingSig<C>,
+    sigma: &LSAGSig<C>,
+) -> Result<(), Errors> {
+
+    // construct pk vectors
+    let pk_gamma = vec![C_prime_i, C_sub_i];
+    let pk_sigma = vec![P_i, C_sub_i];
+
+    gamma.ver_RingSig(message, &pk_gamma)?;
+    sigma.ver_LSAG(message, &pk_sigma)
+}
+
+// An exchange-owned output: its position in the anonymity set, secret key,
+// amount and blinding factor
+#[derive(Clone)]
+pub struct OwnedOutput {
+    pub index: usize,
+    pub x: Scalar,
+    pub amount: u64,
+    pub blinding: Scalar,
+}
+
+// Secret witness: the exchange-owned outputs, sorted by position
+#[derive(Clone)]
+pub struct MProveWitness {
+    owned: Vec<OwnedOutput>,
+}
+
+impl MProveWitness {
+    pub fn new(owned: Vec<OwnedOutput>) -> Result<MProveWitness, Errors> {
+
+        // positions must be strictly increasing (sorted and unique)
+        for pair in owned.windows(2) {
+            if pair[0].index >= pair[1].index {
+                return Err(MProveWitnessError);
+            }
+        }
+        Ok(MProveWitness { owned })
+    }
+
+    // Compatibility adapter for the bit-array form: E_vec[i] & 1 marks
+    // position i as owned, and x_vec, a_vec, r_vec list the secrets of the
+    // owned positions in order
+    pub fn from_E_vec(
+        E_vec: &[u64],
+        x_vec: &[Scalar],
+        a_vec: &[u64],
+        r_vec: &[Scalar],
+    ) -> Result<MProveWitness, Errors> {
+
+        let idx: Vec<usize> = (0..E_vec.len()).filter(|&i| E_vec[i] & 1u64 == 1).collect();
+        let s = idx.len();
+        if x_vec.len() != s || a_vec.len() != s || r_vec.len() != s {
+            return Err(MProveWitnessError);
+        }
+
+        let owned = (0..s)
+            .map(|k| OwnedOutput {
+                index: idx[k],
+                x: x_vec[k],
+                amount: a_vec[k],
+                blinding: r_vec[k],
+            })
+            .collect();
+        MProveWitness::new(owned)
+    }
+
+    pub fn owned(&self) -> &[OwnedOutput] {
+        &self.owned
+    }
+
+    pub fn validate<C: Curve>(&self, stmt: &MProveStatement<C>) -> Result<(), Errors> {
+
+        // every owned address must be in the set and equal x*G, and its
+        // commitment must open to (amount, blinding)
+        for out in self.owned.iter() {
+            if out.index >= stmt.len() || stmt.P_vec[out.index] != stmt.G * out.x {
+                return Err(MProveWitnessError);
+            }
+            if stmt.C_vec[out.index] != stmt.G * out.blinding + stmt.H * Scalar::from(out.amount) {
+                return Err(MProveWitnessError);
+            }
+        }
+
+        // nor may one address be owned at two positions
+        let addresses: HashSet<[u8; 32]> = self.owned.iter().map(|out| C::compress(&stmt.P_vec[out.index])).collect();
+        if addresses.len() != self.owned.len() {
+            return Err(MProveWitnessError);
+        }
+        Ok(())
+    }
+
+    // total amount held in the owned outputs
+    pub fn total_amount(&self) -> Result<u64, Errors> {
+        self.owned
+            .iter()
+            .try_fold(0u64, |total, out| total.checked_add(out.amount))
+            .ok_or(MProveWitnessError)
+    }
+}
 
-use std::cmp;
-use rand::distributions::{Distribution, Uniform};
-// use std::time::{Instant};
-use rand::Rng;
-use curve25519_dalek::constants;
-use sha2::Sha512;
+// Byte counts of the components of an encoded MProve proof
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MProveSize {
+    pub header: usize,
+    pub C_prime_vec: usize,
+    pub C_res: usize,
+    pub gamma_vec: usize,
+    pub sigma_vec: usize,
+    pub identity: usize,
+}
+
+impl MProveSize {
+    pub fn total(&self) -> usize {
+        self.header + self.C_prime_vec + self.C_res + self.gamma_vec + self.sigma_vec + self.identity
+    }
+}
+
+// An exchange's long-term identity key and its Schnorr signature over the
+// proof transcript. The rings already commit to the key through the
+// statement; the signature shows the exchange holds it.
+const IDENTITY_BYTES: usize = POINT_BYTES + SCHNORR_BYTES;
+
+// Schnorr signing domain of identity claims
+const IDENTITY_DOMAIN: &[u8] = b"MProve identity";
 
 #[derive(Clone, Debug)]
-pub struct MProve {
-    C_vec: Vec<RistrettoPoint>,
-    P_vec: Vec<RistrettoPoint>,
-    C_prime_vec: Vec<RistrettoPoint>,
-    C_res: RistrettoPoint,
-    gamma_vec: Vec<RingSig>,
-    sigma_vec: Vec<LSAGSig>,
-    message: RistrettoPoint,
+pub struct MProveProof<C: Curve = Ristretto> {
+    C_prime_vec: Vec<C::Point>,
+    C_res: C::Point,
+    gamma_vec: Vec<RingSig<C>>,
+    sigma_vec: Vec<LSAGSig<C>>,
+    identity: Option<(C::Point, SchnorrSig)>,
 }
 
-impl MProve{
+impl<C: Curve> MProveProof<C>{
 
     pub fn prove(
-        // crs
-        G: &RistrettoPoint,
-        // stmt
-        C_vec: &[RistrettoPoint], // vector of commitments
-        P_vec: &[RistrettoPoint], // addresses in the ring (public keys)
-        // witness
-        x_vec: &[Scalar], // secret keys
-        E_vec: &[u64], // locations of exchange-owned keys
-    ) -> MProve {
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+    ) -> Result<MProveProof<C>, Errors> {
+        MProveProof::prove_with_opening(stmt, witness).map(|(proof, _)| proof)
+    }
+
+    // Same as prove, but also returns the opening of C_res to the total
+    // amount of the owned outputs. The opening contains the aggregate
+    // blinding factor and must be kept secret unless it is to be revealed.
+    pub fn prove_with_opening(
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+    ) -> Result<(MProveProof<C>, CResOpening), Errors> {
+        MProveProof::prove_with_output_openings(stmt, witness).map(|(proof, opening, _)| (proof, opening))
+    }
+
+    // Same as prove_with_opening, and also returns, for every owned output
+    // in position order, the opening of its term C_prime_i - C_i of C_res:
+    // amount a_i and blinding z_i - r_i.
+    pub fn prove_with_output_openings(
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+    ) -> Result<(MProveProof<C>, CResOpening, Vec<CResOpening>), Errors> {
+        MProveProof::prove_with_rng(stmt, witness, &mut rand::thread_rng())
+    }
+
+    // Same as prove_with_output_openings, drawing the z_i and all signature
+    // randomness from rng. A seeded rng makes the proof reproducible, which
+    // is only meant for test vectors.
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        stmt: &MProveStatement<C>,
+        witness: &MProveWitness,
+        rng: &mut R,
+    ) -> Result<(MProveProof<C>, CResOpening, Vec<CResOpening>), Errors> {
+
+        profile_span!("prove");
+
+        stmt.check_generators()?;
+        witness.validate(stmt)?;
+        let amount = witness.total_amount()?;
+
+        let G = stmt.G;
+        let C_vec = &stmt.C_vec;
+        let P_vec = &stmt.P_vec;
+        let mut owned = witness.owned().iter().peekable();
 
         // ring size
-        let n: usize = P_vec.len();
-        let mut rng = rand::thread_rng();
+        let n: usize = stmt.len();
 
-        let mut C_prime_vec: Vec<RistrettoPoint> = vec![*G; n];
-        let mut gamma_vec = (0..n).map(|_| RingSig::initialize(n)).collect::<Vec<RingSig>>();
-        let mut sigma_vec = (0..n).map(|_| LSAGSig::initialize(n)).collect::<Vec<LSAGSig>>();
+        let mut C_prime_vec: Vec<C::Point> = vec![G; n];
+        let mut gamma_vec = (0..n).map(|_| RingSig::initialize(2)).collect::<Vec<RingSig<C>>>();
+        let mut sigma_vec = (0..n).map(|_| LSAGSig::initialize(2)).collect::<Vec<LSAGSig<C>>>();
 
-        let message = RistrettoPoint::random(&mut rng);
-        let z_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let message = stmt.message();
+        let z_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(rng)).collect();
 
-        let mut index: usize = 0;
-        let C_res_vec: Vec<RistrettoPoint> = (0..n)
+        let C_res_vec: Vec<C::Point> = (0..n)
             .map(|i| {
-                let bit: u64 = E_vec[i].clone() & 1u64;
-                if bit==1 {
-                    // compute C_prime_i
-                    C_prime_vec[i] = G * z_vec[i];
-
-                    // construct pk vectors
-                    let C_primei_Ci = C_prime_vec[i] - C_vec[i];
-                    let pk_gamma = vec![C_prime_vec[i], C_primei_Ci];
-                    let pk_sigma = vec![P_vec[i], C_primei_Ci];
-
-                    // gen ring signatures
-                    gamma_vec[i] = RingSig::gen_RingSig(message, &pk_gamma, z_vec[i], 0);
-                    sigma_vec[i] = LSAGSig::gen_LSAG(message, &pk_sigma, x_vec[index], 0);
-                    index = index + 1;
-
-                    C_primei_Ci
-                }
-                else {
-                    // compute C_prime_i
-                    let C_primei_Ci = G * z_vec[i]; 
-                    C_prime_vec[i] = C_primei_Ci + C_vec[i];
-
-                    // construct pk vectors
-                    let pk_gamma = vec![C_prime_vec[i], C_primei_Ci];
-                    let pk_sigma = vec![P_vec[i], C_primei_Ci];
-
-                    // gen ring signatures
-                    gamma_vec[i] = RingSig::gen_RingSig(message, &pk_gamma, z_vec[i], 1);
-                    sigma_vec[i] = LSAGSig::gen_LSAG(message, &pk_sigma, z_vec[i], 1);
-
-                    C_primei_Ci
-                }
+                let x = owned.next_if(|out| out.index == i).map(|out| out.x);
+                let (C_prime_i, gamma, sigma) = prove_position(message, G, C_vec[i], P_vec[i], z_vec[i], x, rng);
+                C_prime_vec[i] = C_prime_i;
+                gamma_vec[i] = gamma;
+                sigma_vec[i] = sigma;
+                C_prime_i - C_vec[i]
             })
             .collect();
         
-        let one_vec: Vec<Scalar> = (0..n).map(|_| Scalar::one()).collect();
-        let C_res = RistrettoPoint::vartime_multiscalar_mul(one_vec.iter(), C_res_vec.iter());
+        let C_res = {
+            profile_span!("C_res");
+            let one_vec: Vec<Scalar> = (0..n).map(|_| Scalar::one()).collect();
+            C::vartime_multiscalar_mul(&one_vec, &C_res_vec)
+        };
+
+        // C_res = (sum(z_i) - sum(r_i)) * G - amount * H
+        let blinding = z_vec.iter().sum::<Scalar>()
+            - witness.owned().iter().map(|out| out.blinding).sum::<Scalar>();
+        let output_openings = witness
+            .owned()
+            .iter()
+            .map(|out| CResOpening { amount: out.amount, blinding: z_vec[out.index] - out.blinding })
+            .collect();
 
-        return MProve{
-            C_vec: C_vec.to_vec(),
-            P_vec: P_vec.to_vec(),
+        let proof = MProveProof{
             C_prime_vec,
             C_res,
             gamma_vec,
             sigma_vec,
-            message,
+            identity: None,
         };
+        Ok((proof, CResOpening { amount, blinding }, output_openings))
+    }
+
+    // Claims the proof of a statement claimed by x*G, by signing the
+    // statement and the proof with x. Any earlier claim is replaced.
+    pub fn sign_identity(&mut self, stmt: &MProveStatement<C>, x: &Scalar) -> Result<(), Errors> {
+        let P = C::basepoint() * *x;
+        if stmt.identity != Some(P) {
+            return Err(MProveIdentityError);
+        }
+        self.identity = None;
+        let signature = SchnorrSig::sign::<C>(x, IDENTITY_DOMAIN, &self.identity_message(stmt));
+        self.identity = Some((P, signature));
+        Ok(())
+    }
+
+    // identity key of the exchange claiming the proof, if any
+    pub fn identity(&self) -> Option<C::Point> {
+        self.identity.as_ref().map(|(P, _)| *P)
     }
 
+    // Verifies the proof, and the identity claim if it carries one. A claim
+    // on a statement without an identity key is verified against the
+    // statement claimed by its key.
     pub fn verify(
         &self,
+        stmt: &MProveStatement<C>,
     ) -> Result<(), Errors> {
+        self.verify_with_identity(stmt, None)
+    }
+
+    // Same as verify, and if `identity` is given the proof must also be
+    // claimed by that key
+    pub fn verify_with_identity(
+        &self,
+        stmt: &MProveStatement<C>,
+        identity: Option<&C::Point>,
+    ) -> Result<(), Errors> {
+
+        profile_span!("verify");
 
         // ring size
-        let n: usize = self.P_vec.len();
-        
+        let n: usize = stmt.len();
+        if self.C_prime_vec.len() != n || self.gamma_vec.len() != n || self.sigma_vec.len() != n {
+            return Err(MProveError);
+        }
+        stmt.check_generators()?;
+
+        let claimed = self.identity();
+        if let (None, Some(P)) = (stmt.identity, claimed) {
+            return self.verify_with_identity(&stmt.claimed_by(P), identity);
+        }
+        if stmt.identity != claimed || (identity.is_some() && identity != claimed.as_ref()) {
+            return Err(MProveIdentityError);
+        }
+
+        let message = stmt.message();
+
         // calculated C_res
-        let C_sub_vec: Vec<RistrettoPoint> = (0..n)
+        let C_sub_vec: Vec<C::Point> = (0..n)
             .map(|i| {
-                self.C_prime_vec[i] - self.C_vec[i]
+                self.C_prime_vec[i] - stmt.C_vec[i]
             })
             .collect();
 
-        let one_vec: Vec<Scalar> = (0..n).map(|_| Scalar::one()).collect();
-        let C_res_comp = RistrettoPoint::vartime_multiscalar_mul(one_vec.iter(), C_sub_vec.iter());
+        let C_res_comp = {
+            profile_span!("C_res");
+            let one_vec: Vec<Scalar> = (0..n).map(|_| Scalar::one()).collect();
+            C::vartime_multiscalar_mul(&one_vec, &C_sub_vec)
+        };
 
         // verify ring signatures
-        for i in 0..n {
+        for (i, C_sub_i) in C_sub_vec.iter().enumerate() {
+            verify_position(message, self.C_prime_vec[i], *C_sub_i, stmt.P_vec[i], &self.gamma_vec[i], &self.sigma_vec[i])?;
+        }
 
-            // construct pk vectors
-            let pk_gamma = vec![self.C_prime_vec[i], C_sub_vec[i]];
-            let pk_sigma = vec![self.P_vec[i], C_sub_vec[i]];
+        // no output may be counted twice
+        let key_images: HashSet<[u8; 32]> = self.sigma_vec.iter().map(|sigma| C::compress(&sigma.key_image())).collect();
+        if key_images.len() != n {
+            return Err(MProveError);
+        }
 
-            let ring_res = self.gamma_vec[i].ver_RingSig(self.message, &pk_gamma);
-            let LSAG_res = self.sigma_vec[i].ver_LSAG(self.message, &pk_sigma);
+        if C_res_comp != self.C_res {
+            return Err(MProveError);
+        }
 
-            assert!(ring_res.is_ok(), "Ring signature verification failed at index {}", i);
-            assert!(LSAG_res.is_ok(), "LSAG signature verification failed at index {}", i);  
+        // the claim is only checked once the proof holds for the statement,
+        // so that a statement mismatch is not reported as a bad claim
+        if let Some((ref P, ref signature)) = self.identity {
+            signature
+                .verify::<C>(P, IDENTITY_DOMAIN, &self.identity_message(stmt))
+                .map_err(|_| MProveIdentityError)?;
         }
+        Ok(())
 
-        if C_res_comp==self.C_res {
-            Ok(())
-        } else {
-            Err(MProveError)
+    }
+
+    // proof assembled from its per-position entries, in position order
+    pub(crate) fn from_entries(entries: Vec<(C::Point, RingSig<C>, LSAGSig<C>)>, C_res: C::Point) -> MProveProof<C> {
+        let mut proof = MProveProof {
+            C_prime_vec: Vec::with_capacity(entries.len()),
+            C_res,
+            gamma_vec: Vec::with_capacity(entries.len()),
+            sigma_vec: Vec::with_capacity(entries.len()),
+            identity: None,
+        };
+        for (C_prime_i, gamma, sigma) in entries {
+            proof.C_prime_vec.push(C_prime_i);
+            proof.gamma_vec.push(gamma);
+            proof.sigma_vec.push(sigma);
         }
+        proof
+    }
 
+    // commitment to the exchange's reserves
+    pub fn C_res(&self) -> C::Point {
+        self.C_res
     }
 
-    pub fn gen_params(n: usize, s: usize) -> (
-        RistrettoPoint, 
-        Vec<RistrettoPoint>,
-        Vec<RistrettoPoint>,
-        Vec<Scalar>,
-        Vec<u64>,) {
-                
-        // generate random amounts in range {0,..,2^{amt_bit_range}}
-        let mut rng = rand::thread_rng();
-        let a_vec: Vec<Scalar> = (0..s).map(|_| Scalar::from(rng.gen::<u32>())).collect();
-        
-        // generate blinding factors
-        let r_vec: Vec<Scalar> = (0..s).map(|_| Scalar::random(&mut rng)).collect();
-
-        // generate secret keys
-        let x_vec: Vec<Scalar> = (0..s).map(|_| Scalar::random(&mut rng)).collect();
-
-        // G, H - curve points for generating outputs and key-images
-        let G = constants::RISTRETTO_BASEPOINT_POINT;
-        let H = RistrettoPoint::hash_from_bytes::<Sha512>(G.compress().as_bytes());
-    
-        // generate P_vec, C_vec
-        let mut P_vec: Vec<RistrettoPoint> = (0..n)
-            .map(|_| {
-                RistrettoPoint::random(&mut rng)
-            })
-            .collect();
+    pub fn C_prime_vec(&self) -> &[C::Point] {
+        &self.C_prime_vec
+    }
 
-        // Select random commitments inclusing those owned by the exchange
-        let mut C_vec_mut: Vec<RistrettoPoint> = (0..n)
-            .map(|_| {
-                RistrettoPoint::random(&mut rng)
-            })
-            .collect();
-        
-        // generate random index vector of size s
-        let setsize = n / s;
-        let mut start_idx = 0;
-        let mut end_idx = cmp::max(1, setsize-1);
-        let idx = (0..s).map(|_| {
-            
-            let dist1 = Uniform::from(start_idx..end_idx);
-            start_idx = setsize + start_idx;
-            end_idx =  cmp::min(n-1, end_idx + setsize);
-
-            dist1.sample(&mut rng)
-        })
-        .collect::<Vec<usize>>();
+    // key images of the LSAG signatures, one per anonymity set position
+    pub fn key_images(&self) -> Vec<C::Point> {
+        self.sigma_vec.iter().map(|sigma| sigma.key_image()).collect()
+    }
 
-        let mut index = 0;
-        let E_vec = (0..n)
-            .map(|i| {
-                if index < idx.len() {
-                    if i == idx[index] {
-                        // generate commitments using a_vec, r_vec
-                        C_vec_mut[i as usize] = G * r_vec[index] + H * a_vec[index];
-                        P_vec[i as usize] = G * x_vec[index];
-                        index = index + 1;
-                        1 as u64
-                    }
-                    else {
-                        0u64
-                    }
-                }
-                else{
-                    0u64
-                }
-            })
-            .collect::<Vec<u64>>();
+    // What the identity key signs: the digest of the claimed statement and
+    // the encoding of the proof without its claim
+    fn identity_message(&self, stmt: &MProveStatement<C>) -> [u8; 32] {
+        let unclaimed = MProveProof { identity: None, ..self.clone() };
+        let mut transcript = Transcript::<C>::new(b"MProve proof");
+        transcript.append_message(b"statement", &stmt.digest());
+        transcript.append_message(b"proof", &unclaimed.to_bytes());
+        transcript.challenge_bytes(b"message")
+    }
 
-        (G, C_vec_mut, P_vec, x_vec, E_vec)
+    pub fn to_bytes(&self) -> Vec<u8> {
+
+        // canonical encoding: anonymity set size as a little-endian u32,
+        // C_prime_vec, C_res, gamma_vec, sigma_vec, then the identity key
+        // and its signature if the proof is claimed
+        let n = self.C_prime_vec.len();
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.size_in_bytes().total());
+        bytes.extend_from_slice(&(n as u32).to_le_bytes());
+        for C_prime_i in self.C_prime_vec.iter() {
+            bytes.extend_from_slice(&C::compress(C_prime_i));
+        }
+        bytes.extend_from_slice(&C::compress(&self.C_res));
+        for gamma in self.gamma_vec.iter() {
+            bytes.extend_from_slice(&gamma.to_bytes());
+        }
+        for sigma in self.sigma_vec.iter() {
+            bytes.extend_from_slice(&sigma.to_bytes());
+        }
+        if let Some((ref P, ref signature)) = self.identity {
+            bytes.extend_from_slice(&C::compress(P));
+            bytes.extend_from_slice(&signature.to_bytes());
+        }
+        bytes
+    }
+
+    // Decodes to_bytes() from untrusted input. Every ring in an MProve
+    // proof has two members, so the set size fixes the whole length, up to
+    // the identity claim. No length of an unclaimed proof is that of a
+    // claimed one.
+    pub fn from_bytes(bytes: &[u8]) -> Result<MProveProof<C>, Errors> {
+        let entry_len = POINT_BYTES + RingSig::<C>::encoded_len(2).unwrap() + LSAGSig::<C>::encoded_len(2).unwrap();
+        let len = |n: usize, claim: usize| n.checked_mul(entry_len)?.checked_add(LEN_BYTES + POINT_BYTES + claim);
+        let (n, claimed) = match checked_len(bytes, |n| len(n, 0)) {
+            Some(n) => (n, false),
+            None => (checked_len(bytes, |n| len(n, IDENTITY_BYTES)).ok_or(MProveError)?, true),
+        };
+
+        let reader = &mut &bytes[LEN_BYTES..];
+        let C_prime_vec = (0..n).map(|_| read_point::<C, _>(reader)).collect::<Result<Vec<C::Point>, Errors>>()?;
+        let C_res = read_point::<C, _>(reader)?;
+        let gamma_vec = (0..n).map(|_| RingSig::read_from(reader, 2)).collect::<Result<Vec<RingSig<C>>, Errors>>()?;
+        let sigma_vec = (0..n).map(|_| LSAGSig::read_from(reader, 2)).collect::<Result<Vec<LSAGSig<C>>, Errors>>()?;
+        let identity = if claimed {
+            let P = read_point::<C, _>(reader)?;
+            Some((P, SchnorrSig::from_bytes(reader)?))
+        } else {
+            None
+        };
+        Ok(MProveProof {
+            C_prime_vec,
+            C_res,
+            gamma_vec,
+            sigma_vec,
+            identity,
+        })
+    }
+
+    pub fn size_in_bytes(&self) -> MProveSize {
+        // component sizes of to_bytes(), computed without building the encoding
+        MProveSize {
+            header: LEN_BYTES,
+            C_prime_vec: self.C_prime_vec.len() * POINT_BYTES,
+            C_res: POINT_BYTES,
+            gamma_vec: self.gamma_vec.iter().map(|gamma| gamma.size_in_bytes()).sum(),
+            sigma_vec: self.sigma_vec.iter().map(|sigma| sigma.size_in_bytes()).sum(),
+            identity: if self.identity.is_some() { IDENTITY_BYTES } else { 0 },
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::cmp;
-    use rand::distributions::{Distribution, Uniform};
     use std::time::{Instant};
-    use rand::Rng;
-    use curve25519_dalek::constants;
-    use sha2::Sha512;
-    
-    use std::fs::File;
-    use flame as f;
+    use Errors::MProveSigsError;
+    use proofs::backend::MoneroEd25519;
+    use proofs::fixtures::Fixture;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
 
-    pub fn test_mprove(n: usize, s: usize){
-                
-        // generate random amounts in range {0,..,2^{amt_bit_range}}
-        let mut rng = rand::thread_rng();
-        let a_vec: Vec<Scalar> = (0..s).map(|_| Scalar::from(rng.gen::<u32>())).collect();
-        
-        // generate blinding factors
-        let r_vec: Vec<Scalar> = (0..s).map(|_| Scalar::random(&mut rng)).collect();
-
-        // generate secret keys
-        let x_vec: Vec<Scalar> = (0..s).map(|_| Scalar::random(&mut rng)).collect();
-
-        // G, H - curve points for generating outputs and key-images
-        let G = constants::RISTRETTO_BASEPOINT_POINT;
-        let H = RistrettoPoint::hash_from_bytes::<Sha512>(G.compress().as_bytes());
-    
-        // generate P_vec, C_vec
-        let mut P_vec: Vec<RistrettoPoint> = (0..n)
-            .map(|_| {
-                RistrettoPoint::random(&mut rng)
-            })
-            .collect();
 
-        // Select random commitments inclusing those owned by the exchange
-        let mut C_vec_mut: Vec<RistrettoPoint> = (0..n)
-            .map(|_| {
-                RistrettoPoint::random(&mut rng)
-            })
-            .collect();
-        
-        // generate random index vector of size s
-        let setsize = n / s;
-        let mut start_idx = 0;
-        let mut end_idx = cmp::max(1, setsize-1);
-        let idx = (0..s).map(|_| {
-            
-            let dist1 = Uniform::from(start_idx..end_idx);
-            start_idx = setsize + start_idx;
-            end_idx =  cmp::min(n-1, end_idx + setsize);
-
-            dist1.sample(&mut rng)
-        })
-        .collect::<Vec<usize>>();
+    pub fn test_mprove(n: usize, s: usize){
+        let Fixture { stmt, witness } = Fixture::<Ristretto>::generate(n, s, 0).unwrap();
+        let stmt = MProveStatement { context: b"test_mprove".to_vec(), ..stmt };
 
-        let mut index = 0;
-        let E_vec = (0..n)
-            .map(|i| {
-                if index < idx.len() {
-                    if i == idx[index] {
-                        // generate commitments using a_vec, r_vec
-                        C_vec_mut[i as usize] = G * r_vec[index] + H * a_vec[index];
-                        P_vec[i as usize] = G * x_vec[index];
-                        index = index + 1;
-                        1 as u64
-                    }
-                    else {
-                        0u64
-                    }
-                }
-                else{
-                    0u64
-                }
-            })
-            .collect::<Vec<u64>>();            
-        
-        // let _fg = ::flame::start_guard("test_mprove");
-        
         println!("(n={}, s={})", n, s);
         let start = Instant::now();
-        let mprove_sample = MProve::prove(&G, &C_vec_mut, &P_vec, &x_vec, &E_vec);
+        let mprove_sample = MProveProof::prove(&stmt, &witness).unwrap();
         let duration = start.elapsed();
         println!("MProve gen time: {:?}", duration);
 
         let start = Instant::now();
-        let result = mprove_sample.verify();
+        let result = mprove_sample.verify(&stmt);
         let duration = start.elapsed();
         println!("MProve ver time: {:?}\n", duration);
 
         assert!(result.is_ok());
+
+        // the proof is only valid for the statement it was made for
+        let mut other_stmt = stmt.clone();
+        other_stmt.C_vec.swap(0, 1);
+        assert!(mprove_sample.verify(&other_stmt).is_err());
+        other_stmt = MProveStatement { context: b"other".to_vec(), ..stmt.clone() };
+        assert!(mprove_sample.verify(&other_stmt).is_err());
+
+        assert_eq!(mprove_sample.to_bytes().len(), 4 + n * (32 + 100 + 132) + 32);
+        assert_eq!(mprove_sample.size_in_bytes().total(), mprove_sample.to_bytes().len());
     }
 
     #[test]
@@ -326,15 +691,313 @@ mod tests {
         test_mprove(1000, 100);
     }
 
+    #[test]
+    pub fn sim_mprove_monero(){
+        let Fixture { stmt, witness } = Fixture::<MoneroEd25519>::generate(8, 3, 1).unwrap();
+        assert_eq!(stmt.H, MoneroEd25519::amount_generator());
+        let proof = MProveProof::prove(&stmt, &witness).unwrap();
+        assert!(proof.verify(&stmt).is_ok());
+
+        // owned outputs reveal their Monero key images x * hash_to_ec(P)
+        for out in witness.owned() {
+            let I = MoneroEd25519::key_image_base(&stmt.P_vec[out.index]) * out.x;
+            assert!(proof.sigma_vec.iter().any(|sigma| sigma.key_image() == I));
+        }
+    }
+
+    #[test]
+    pub fn invalid_witness(){
+        let Fixture { stmt, witness } = Fixture::<Ristretto>::generate(10, 2, 2).unwrap();
+        assert!(witness.validate(&stmt).is_ok());
+
+        // wrong secret key
+        let mut owned = witness.owned().to_vec();
+        owned[1].x += Scalar::one();
+        let bad_witness = MProveWitness::new(owned).unwrap();
+        assert_eq!(MProveProof::prove(&stmt, &bad_witness).err(), Some(MProveWitnessError));
+
+        // position outside the anonymity set
+        let mut owned = witness.owned().to_vec();
+        owned[1].index = stmt.len();
+        let bad_witness = MProveWitness::new(owned).unwrap();
+        assert_eq!(MProveProof::prove(&stmt, &bad_witness).err(), Some(MProveWitnessError));
+
+        // unsorted and duplicate positions
+        let mut owned = witness.owned().to_vec();
+        owned.swap(0, 1);
+        assert_eq!(MProveWitness::new(owned).err(), Some(MProveWitnessError));
+        let mut owned = witness.owned().to_vec();
+        owned[1].index = owned[0].index;
+        assert_eq!(MProveWitness::new(owned).err(), Some(MProveWitnessError));
+
+        // one owned output listed at two positions
+        let out = witness.owned()[0].clone();
+        let j = (0..stmt.len()).find(|&j| witness.owned().iter().all(|out| out.index != j)).unwrap();
+        let (mut C_vec, mut P_vec) = (stmt.C_vec.clone(), stmt.P_vec.clone());
+        C_vec[j] = C_vec[out.index];
+        P_vec[j] = P_vec[out.index];
+        assert_eq!(MProveStatement::<Ristretto>::new(stmt.G, stmt.H, C_vec.clone(), P_vec.clone(), b"").err(), Some(MProveError));
+        let twice = MProveStatement { C_vec, P_vec, ..stmt.clone() };
+        let copy = OwnedOutput { index: j, ..out.clone() };
+        let mut owned = vec![out.clone(), copy.clone()];
+        owned.sort_by_key(|out| out.index);
+        let both = MProveWitness::new(owned).unwrap();
+        assert_eq!(MProveProof::prove(&twice, &both).err(), Some(MProveWitnessError));
+
+        // and a proof spliced from one proof for each copy
+        let first = MProveProof::prove(&twice, &MProveWitness::new(vec![out]).unwrap()).unwrap();
+        let second = MProveProof::prove(&twice, &MProveWitness::new(vec![copy]).unwrap()).unwrap();
+        let mut spliced = first.clone();
+        spliced.C_prime_vec[j] = second.C_prime_vec[j];
+        spliced.gamma_vec[j] = second.gamma_vec[j].clone();
+        spliced.sigma_vec[j] = second.sigma_vec[j].clone();
+        spliced.C_res = first.C_res + second.C_prime_vec[j] - first.C_prime_vec[j];
+        assert!(first.verify(&twice).is_ok());
+        assert_eq!(spliced.verify(&twice), Err(MProveError));
+
+        // bit-array adapter
+        let E_vec: Vec<u64> = (0..stmt.len())
+            .map(|i| witness.owned().iter().any(|out| out.index == i) as u64)
+            .collect();
+        let x_vec: Vec<Scalar> = witness.owned().iter().map(|out| out.x).collect();
+        let a_vec: Vec<u64> = witness.owned().iter().map(|out| out.amount).collect();
+        let r_vec: Vec<Scalar> = witness.owned().iter().map(|out| out.blinding).collect();
+        let adapted = MProveWitness::from_E_vec(&E_vec, &x_vec, &a_vec, &r_vec).unwrap();
+        assert_eq!(adapted.owned().len(), 2);
+        assert!(adapted.validate(&stmt).is_ok());
+        assert!(MProveWitness::from_E_vec(&E_vec, &x_vec[..1], &a_vec, &r_vec).is_err());
+    }
+
+    // s + 1 in place of the scalar at `offset` of an encoding
+    fn bump_scalar(bytes: &mut [u8], offset: usize) {
+        let mut buf = [0u8; 32];
+        buf.copy_from_slice(&bytes[offset..offset + 32]);
+        let s = Scalar::from_canonical_bytes(buf).unwrap() + Scalar::one();
+        bytes[offset..offset + 32].copy_from_slice(s.as_bytes());
+    }
+
+    pub fn test_tampered_mprove<C: Curve>(n: usize, s: usize){
+        let Fixture { stmt, witness } = Fixture::<C>::generate(n, s, 4).unwrap();
+        let proof = MProveProof::prove(&stmt, &witness).unwrap();
+        assert!(proof.verify(&stmt).is_ok());
+        let mut rng = rand::thread_rng();
+
+        // each s_i and c of every gamma_i and sigma_i
+        for i in 0..n {
+            for offset in (0..3).map(|j| 4 + 32 * j) {
+                let mut bad = proof.clone();
+                let mut bytes = bad.gamma_vec[i].to_bytes();
+                bump_scalar(&mut bytes, offset);
+                bad.gamma_vec[i] = RingSig::read_from(&mut &bytes[..], 2).unwrap();
+                assert_eq!(bad.verify(&stmt), Err(MProveSigsError));
+
+                let mut bad = proof.clone();
+                let mut bytes = bad.sigma_vec[i].to_bytes();
+                bump_scalar(&mut bytes, offset);
+                bad.sigma_vec[i] = LSAGSig::read_from(&mut &bytes[..], 2).unwrap();
+                assert_eq!(bad.verify(&stmt), Err(MProveSigsError));
+            }
+        }
+
+        // swapped C_prime_i, which leaves their sum unchanged
+        let mut bad = proof.clone();
+        bad.C_prime_vec.swap(0, n - 1);
+        assert_eq!(bad.verify(&stmt), Err(MProveSigsError));
+
+        // altered C_res, alone or with a C_prime_i to match
+        let mut bad = proof.clone();
+        bad.C_res = bad.C_res + stmt.G;
+        assert_eq!(bad.verify(&stmt), Err(MProveError));
+        bad.C_prime_vec[0] = bad.C_prime_vec[0] + stmt.G;
+        assert_eq!(bad.verify(&stmt), Err(MProveSigsError));
+
+        // another message
+        let other = MProveStatement { context: b"other".to_vec(), ..stmt.clone() };
+        assert_eq!(proof.verify(&other), Err(MProveSigsError));
+
+        // replaced or swapped key images
+        let mut bad = proof.clone();
+        let mut bytes = bad.sigma_vec[0].to_bytes();
+        bytes[4 + 3 * 32..].copy_from_slice(&C::compress(&C::random_point(&mut rng)));
+        bad.sigma_vec[0] = LSAGSig::read_from(&mut &bytes[..], 2).unwrap();
+        assert_eq!(bad.verify(&stmt), Err(MProveSigsError));
+        let mut bad = proof.clone();
+        bad.sigma_vec.swap(0, n - 1);
+        assert_eq!(bad.verify(&stmt), Err(MProveSigsError));
+
+        // truncated vectors
+        let mut bad = proof.clone();
+        bad.gamma_vec.pop();
+        assert_eq!(bad.verify(&stmt), Err(MProveError));
+        let mut bad = proof.clone();
+        bad.C_prime_vec.pop();
+        assert_eq!(bad.verify(&stmt), Err(MProveError));
+        let mut bad = proof.clone();
+        bad.sigma_vec.truncate(1);
+        assert_eq!(bad.verify(&stmt), Err(MProveError));
+        let short = MProveStatement {
+            C_vec: stmt.C_vec[..n - 1].to_vec(),
+            P_vec: stmt.P_vec[..n - 1].to_vec(),
+            ..stmt.clone()
+        };
+        assert_eq!(proof.verify(&short), Err(MProveError));
+
+        // an owned position signed with a wrong secret key
+        let out = &witness.owned()[0];
+        let i = out.index;
+        let z_i = Scalar::random(&mut rng);
+        let wrong_x = Some(out.x + Scalar::one());
+        let (C_prime_i, gamma, sigma) = prove_position::<C, _>(stmt.message(), stmt.G, stmt.C_vec[i], stmt.P_vec[i], z_i, wrong_x, &mut rng);
+        let mut bad = proof.clone();
+        bad.C_res = bad.C_res - bad.C_prime_vec[i] + C_prime_i;
+        bad.C_prime_vec[i] = C_prime_i;
+        bad.gamma_vec[i] = gamma;
+        bad.sigma_vec[i] = sigma;
+        assert_eq!(bad.verify(&stmt), Err(MProveSigsError));
+    }
+
+    #[test]
+    pub fn tampered_mprove(){
+        test_tampered_mprove::<Ristretto>(6, 2);
+        test_tampered_mprove::<MoneroEd25519>(4, 4);
+    }
+
+    #[test]
+    pub fn decode_untrusted(){
+        let Fixture { stmt, witness } = Fixture::<Ristretto>::generate(4, 2, 5).unwrap();
+        let proof = MProveProof::prove(&stmt, &witness).unwrap();
+        let bytes = proof.to_bytes();
+        assert_eq!(MProveProof::<Ristretto>::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+
+        // truncated, extended, or with a length prefix the input cannot hold
+        for len in 0..bytes.len() {
+            assert!(MProveProof::<Ristretto>::from_bytes(&bytes[..len]).is_err());
+        }
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert_eq!(MProveProof::<Ristretto>::from_bytes(&extended).err(), Some(MProveError));
+        let mut huge = bytes.clone();
+        huge[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(MProveProof::<Ristretto>::from_bytes(&huge).err(), Some(MProveError));
+        let sig = proof.sigma_vec[0].to_bytes();
+        assert_eq!(LSAGSig::<Ristretto>::from_bytes(&sig).unwrap().to_bytes(), sig);
+        assert_eq!(RingSig::<Ristretto>::from_bytes(&sig).err(), Some(MProveSigsError));
+        assert_eq!(RingSig::<Ristretto>::from_bytes(&huge[..4]).err(), Some(MProveSigsError));
+
+        // no mutation of a valid proof is accepted, the same harness as the
+        // mutate_mprove fuzz target
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..200 {
+            let mut mutated = bytes.clone();
+            let k = rng.gen_range(0, mutated.len());
+            mutated[k] ^= 1 << rng.gen_range(0, 8);
+            if let Ok(decoded) = MProveProof::<Ristretto>::from_bytes(&mutated) {
+                assert!(decoded.verify(&stmt).is_err());
+            }
+        }
+    }
+
+    #[test]
+    pub fn named_generators(){
+        let Fixture { stmt, witness } = Fixture::<Ristretto>::generate(6, 2, 3).unwrap();
+        assert_eq!(stmt.generators, Some(STANDARD.to_vec()));
+
+        // the owned outputs recommitted under another set's H
+        let generators = Generators::<Ristretto>::from_name(b"audit");
+        let mut C_vec = stmt.C_vec.clone();
+        for out in witness.owned().iter() {
+            C_vec[out.index] = stmt.G * out.blinding + generators.H() * Scalar::from(out.amount);
+        }
+        let named = MProveStatement::from_generators(&generators, C_vec, stmt.P_vec.clone(), b"named").unwrap();
+        let proof = MProveProof::prove(&named, &witness).unwrap();
+        assert!(proof.verify(&named).is_ok());
+
+        // the proof is bound to the set, and H must be the set's
+        let renamed = MProveStatement { generators: None, ..named.clone() };
+        assert!(proof.verify(&renamed).is_err());
+        let wrong_H = MProveStatement { H: stmt.H, ..named.clone() };
+        assert_eq!(proof.verify(&wrong_H), Err(MProveError));
+        assert_eq!(MProveProof::prove(&wrong_H, &witness).err(), Some(MProveError));
+    }
+
+    pub fn test_identity<C: Curve>(){
+        let Fixture { stmt, witness } = Fixture::<C>::generate(5, 2, 4).unwrap();
+        let mut rng = rand::thread_rng();
+        let x = Scalar::random(&mut rng);
+        let exchange = C::basepoint() * x;
+        let other = C::basepoint() * Scalar::random(&mut rng);
+
+        // an unclaimed proof verifies, but not as any exchange's
+        let unclaimed = MProveProof::prove(&stmt, &witness).unwrap();
+        assert_eq!(unclaimed.identity(), None);
+        assert_eq!(unclaimed.verify_with_identity(&stmt, Some(&exchange)), Err(MProveIdentityError));
+        let mut relabelled = unclaimed.clone();
+        assert_eq!(relabelled.sign_identity(&stmt, &x), Err(MProveIdentityError));
+
+        // a proof of the claimed statement, signed by the exchange
+        let claimed_stmt = stmt.claimed_by(exchange);
+        let mut proof = MProveProof::prove(&claimed_stmt, &witness).unwrap();
+        assert_eq!(proof.verify(&claimed_stmt), Err(MProveIdentityError));
+        assert_eq!(proof.sign_identity(&claimed_stmt, &Scalar::random(&mut rng)), Err(MProveIdentityError));
+        proof.sign_identity(&claimed_stmt, &x).unwrap();
+        assert_eq!(proof.identity(), Some(exchange));
+        assert!(proof.verify(&stmt).is_ok());
+        assert!(proof.verify(&claimed_stmt).is_ok());
+        assert!(proof.verify_with_identity(&stmt, Some(&exchange)).is_ok());
+        assert_eq!(proof.verify_with_identity(&stmt, Some(&other)), Err(MProveIdentityError));
+        assert_eq!(proof.verify(&stmt.claimed_by(other)), Err(MProveIdentityError));
+        assert_eq!(proof.size_in_bytes().total(), proof.to_bytes().len());
+
+        // the claim survives encoding, and is bound to the statement and proof
+        let decoded = MProveProof::<C>::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded.to_bytes(), proof.to_bytes());
+        assert!(decoded.verify_with_identity(&stmt, Some(&exchange)).is_ok());
+        let other_stmt = MProveStatement { context: b"other".to_vec(), ..stmt.clone() };
+        assert_eq!(proof.verify(&other_stmt), Err(MProveSigsError));
+        let mut bytes = proof.to_bytes();
+        let k = bytes.len() - 1;
+        bytes[k] ^= 1;
+        assert!(MProveProof::<C>::from_bytes(&bytes).and_then(|p| p.verify(&stmt)).is_err());
+
+        // a claim moved onto another proof of the same statement is rejected
+        let mut stolen = MProveProof::prove(&claimed_stmt, &witness).unwrap();
+        stolen.identity = proof.identity.clone();
+        assert_eq!(stolen.verify_with_identity(&stmt, Some(&exchange)), Err(MProveIdentityError));
+        assert_eq!(stolen.verify(&stmt), Err(MProveIdentityError));
+
+        // a claim stripped and re-signed under another key is rejected, as
+        // the rings commit to the original key
+        let y = Scalar::random(&mut rng);
+        let thief = C::basepoint() * y;
+        let mut resigned = proof.clone();
+        resigned.identity = None;
+        resigned.sign_identity(&stmt.claimed_by(thief), &y).unwrap();
+        assert_eq!(resigned.identity(), Some(thief));
+        assert!(resigned.verify_with_identity(&stmt, Some(&thief)).is_err());
+        assert!(resigned.verify(&stmt).is_err());
+        let mut unclaimed_copy = proof.clone();
+        unclaimed_copy.identity = None;
+        assert_eq!(unclaimed_copy.verify(&stmt), Err(MProveSigsError));
+    }
+
+    #[test]
+    pub fn identity_claims(){
+        test_identity::<Ristretto>();
+        test_identity::<MoneroEd25519>();
+    }
+
+    #[cfg(feature = "profiling")]
     #[test]
     pub fn gen_profile(){
-        let _fg = ::flame::start_guard("sim_mprove");
-        test_mprove(2, 1);
-        _fg.end();
+        {
+            profile_span!("sim_mprove");
+            test_mprove(2, 1);
+        }
 
-        // save flamegraph to a json file
-        f::dump_html(File::create("flamegraph.html").unwrap()).unwrap();
-        f::dump_json(&mut File::create("flamegraph.json").unwrap()).unwrap();
+        // save flamegraph outside of the source tree
+        let dir = std::env::temp_dir();
+        ::profiling::dump_html(dir.join("mprove_flamegraph.html")).unwrap();
+        ::profiling::dump_json(dir.join("mprove_flamegraph.json")).unwrap();
     }
 
 }
\ No newline at end of file
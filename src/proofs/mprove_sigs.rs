@@ -11,54 +11,116 @@ This file is part of mProve library
 
 // based on the paper: <link to paper>
 
-use Errors::{self, MProveSigsError};
-use curve25519_dalek::ristretto::{RistrettoPoint};
-use curve25519_dalek::traits::VartimeMultiscalarMul;
+use Errors::{self, MProveIoError, MProveSigsError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::transcript::Transcript;
 use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::constants;
-// use sha2::Sha512;
-use sha3::Keccak512;
+use rand::{CryptoRng, RngCore};
+use std::io::Read;
+use std::marker::PhantomData;
+
+
+// sizes used by the canonical encodings
+pub const POINT_BYTES: usize = 32;
+pub const SCALAR_BYTES: usize = 32;
+pub const LEN_BYTES: usize = 4;
+
+// readers for the canonical encodings; short reads are MProveIoError,
+// malformed values MProveSigsError
+pub fn read_len<R: Read>(reader: &mut R) -> Result<usize, Errors> {
+    let mut buf = [0u8; LEN_BYTES];
+    reader.read_exact(&mut buf).map_err(|_| MProveIoError)?;
+    Ok(u32::from_le_bytes(buf) as usize)
+}
+
+pub fn read_scalar<R: Read>(reader: &mut R) -> Result<Scalar, Errors> {
+    let mut buf = [0u8; SCALAR_BYTES];
+    reader.read_exact(&mut buf).map_err(|_| MProveIoError)?;
+    Scalar::from_canonical_bytes(buf).ok_or(MProveSigsError)
+}
+
+pub fn read_point<C: Curve, R: Read>(reader: &mut R) -> Result<C::Point, Errors> {
+    let mut buf = [0u8; POINT_BYTES];
+    reader.read_exact(&mut buf).map_err(|_| MProveIoError)?;
+    C::decompress(&buf).ok_or(MProveSigsError)
+}
 
+// Ring size of an encoding read from untrusted bytes, accepted only if the
+// input is exactly `len(n)` bytes long, so that a length prefix can never
+// make a decoder allocate more than the input
+pub(crate) fn checked_len<F: Fn(usize) -> Option<usize>>(bytes: &[u8], len: F) -> Option<usize> {
+    if bytes.len() < LEN_BYTES {
+        return None;
+    }
+    let n = read_len(&mut &bytes[..]).ok()?;
+    match len(n) {
+        Some(expected) if expected == bytes.len() => Some(n),
+        _ => None,
+    }
+}
 
 #[derive(Clone, Debug)]
-pub struct RingSig{
+pub struct RingSig<C: Curve = Ristretto>{
     s_vec: Vec<Scalar>,
     c: Scalar,
+    _curve: PhantomData<C>,
 }
 
-impl RingSig{
+impl<C: Curve> RingSig<C>{
     pub fn gen_RingSig(
-        message: RistrettoPoint,
-        pk: &[RistrettoPoint],
+        message: C::Point,
+        pk: &[C::Point],
+        x: Scalar,
+        index: usize,
+    ) -> RingSig<C> {
+        RingSig::gen_RingSig_with_rng(message, pk, x, index, &mut rand::thread_rng())
+    }
+
+    // Same as gen_RingSig, drawing alpha and the s_i from rng
+    pub fn gen_RingSig_with_rng<R: RngCore + CryptoRng>(
+        message: C::Point,
+        pk: &[C::Point],
         x: Scalar,
         index: usize,
-    ) -> RingSig {
+        rng: &mut R,
+    ) -> RingSig<C> {
+
+        profile_span!("gen RS");
+
+        // Pick alpha
+        let alpha = Scalar::random(rng);
+
+        // Compute L_j
+        let L_j = C::basepoint() * alpha;
 
-        // let _fg = ::flame::start_guard("gen RS");
+        // compute s_j
+        let (mut sig, c_j) = RingSig::close_ring(message, pk, index, L_j, rng);
+        sig.s_vec[index] = alpha - (c_j * x);
+        sig
+    }
+
+    // Completes the ring from the signer's commitment L_index = alpha*G:
+    // picks the s_i of the other members and returns the signature, with
+    // s_index still to be set to alpha - c_index*x, and c_index.
+    pub(crate) fn close_ring<R: RngCore + CryptoRng>(
+        message: C::Point,
+        pk: &[C::Point],
+        index: usize,
+        L_j: C::Point,
+        rng: &mut R,
+    ) -> (RingSig<C>, Scalar) {
 
         // ring size
         let n = pk.len();
-        let mut rng = rand::thread_rng();
         assert!(n >= 2, "Error! Why ring signature if cols = 1!");
         assert!(index < n, "Index out of range");
 
-        // Pick alpha and s_i \in {1,2,...,q-1}, i = 0,1,...,n-1
-        let alpha = Scalar::random(&mut rng);
-        let mut s_vec: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
-
-        // Compute L_j
-        let G = constants::RISTRETTO_BASEPOINT_POINT;
-        let L_j = G * alpha;
-        
-        // Construct a vector of EC points to hash
-        let mut tohash_vec: Vec<u8> = Vec::new();
-        for i in 0..n {
-            tohash_vec.extend_from_slice(pk[i].compress().as_bytes());
-        }
-        tohash_vec.extend_from_slice(message.compress().as_bytes());
-        tohash_vec.extend_from_slice(L_j.compress().as_bytes());
+        // Pick s_i \in {1,2,...,q-1}, i = 0,1,...,n-1
+        let mut s_vec: Vec<_> = (0..n).map(|_| Scalar::random(rng)).collect();
 
-        let mut c_old = Scalar::hash_from_bytes::<Keccak512>(&tohash_vec);
+        // transcript of the ring, shared by every challenge
+        let transcript = RingSig::<C>::transcript(message, pk);
+        let mut c_old = RingSig::<C>::challenge(&transcript, &L_j);
 
         // compute c0
         let mut j = (index + 1)%n;
@@ -70,16 +132,14 @@ impl RingSig{
         // computing (j mod n) points and integers
         while j != index {
             // compute L
-            s_vec[j] = Scalar::random(&mut rng);
+            s_vec[j] = Scalar::random(rng);
             // let sG = G * s_vec[j];
             // let cpk_j = pk[j] * c_old;
             // let L = sG + cpk_j;
-            let L = RistrettoPoint::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &s_vec[j]);
+            let L = C::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &s_vec[j]);
 
             // compute c_old
-            let idx = (n+1)*32;
-            tohash_vec[idx..].copy_from_slice(L.compress().as_bytes());
-            c_old = Scalar::hash_from_bytes::<Keccak512>(&tohash_vec);
+            c_old = RingSig::<C>::challenge(&transcript, &L);
             
             j = (j + 1) % n;
 
@@ -88,55 +148,98 @@ impl RingSig{
             }
         }
 
-        // compute s_j
-        s_vec[index] = alpha - (c_old * x);
-
-        return RingSig {
+        let sig = RingSig {
             s_vec,
             c,
+            _curve: PhantomData,
         };
+        (sig, c_old)
+    }
+
+    // sets s_index of a signature from close_ring
+    pub(crate) fn set_response(&mut self, index: usize, s: Scalar) {
+        self.s_vec[index] = s;
+    }
+
+    // Recomputes c_index of a signature from close_ring, walking the ring
+    // on from the commitment L_index, which must meet c at position 0
+    pub(crate) fn challenge_at(
+        &self,
+        message: C::Point,
+        pk: &[C::Point],
+        index: usize,
+        L_j: C::Point,
+    ) -> Result<Scalar, Errors> {
+
+        let n = pk.len();
+        if n < 2 || index >= n || self.s_vec.len() != n {
+            return Err(MProveSigsError);
+        }
+
+        let transcript = RingSig::<C>::transcript(message, pk);
+        let mut c_old = RingSig::<C>::challenge(&transcript, &L_j);
+        let mut j = (index + 1) % n;
+        loop {
+            if j == 0 && c_old != self.c {
+                return Err(MProveSigsError);
+            }
+            if j == index {
+                return Ok(c_old);
+            }
+            let L = C::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &self.s_vec[j]);
+            c_old = RingSig::<C>::challenge(&transcript, &L);
+            j = (j + 1) % n;
+        }
+    }
+
+    // ring size, ring and message
+    fn transcript(message: C::Point, pk: &[C::Point]) -> Transcript<C> {
+        let mut transcript = Transcript::new(b"RingSig");
+        transcript.append_points(b"pk", pk);
+        transcript.append_point(b"message", &message);
+        transcript
+    }
+
+    // challenge following the member with commitment L
+    fn challenge(transcript: &Transcript<C>, L: &C::Point) -> Scalar {
+        let mut transcript = transcript.clone();
+        transcript.append_point(b"L", L);
+        transcript.challenge_scalar(b"c")
     }
 
     pub fn ver_RingSig(
         &self,
-        message: RistrettoPoint,
-        pk: &[RistrettoPoint],
+        message: C::Point,
+        pk: &[C::Point],
     ) -> Result<(), Errors> {
 
-        // let _fg = ::flame::start_guard("ver RS");
+        profile_span!("ver RS");
         
-        // ring size
+        // ring size; a malformed signature is rejected, not a panic
         let n = pk.len();
-        assert!(n >= 2, "Error! Why ring signature if cols = 1!");
-        assert!(self.s_vec.len() == n, "Bad rs.s_vec size.");
+        if n < 2 || self.s_vec.len() != n {
+            return Err(MProveSigsError);
+        }
 
         // check if any element of s_vec or c is 0
-        for i in 0..n {
-            assert!(self.s_vec[i] != Scalar::zero(), "s_vec cannot have a 0 element!");
+        if self.c == Scalar::zero() || self.s_vec.iter().any(|s_i| *s_i == Scalar::zero()) {
+            return Err(MProveSigsError);
         }
-        assert!(self.c != Scalar::zero(), "c cannot be 0!");
 
-        // Construct a vector of EC points to hash
-        let mut tohash_vec: Vec<u8> = Vec::new();
-        for i in 0..n {
-            tohash_vec.extend_from_slice(pk[i].compress().as_bytes());
-        }
-        tohash_vec.extend_from_slice(message.compress().as_bytes());
-        tohash_vec.extend_from_slice(message.compress().as_bytes());
+        // transcript of the ring, shared by every challenge
+        let transcript = RingSig::<C>::transcript(message, pk);
 
         // compute c_j's
         let mut j: usize = 0;
         let mut c_old = self.c;
-        let idx = (n+1)*32;
         while j < n {
             // let sG = G * self.s_vec[j];
             // let cpk_j = pk[j] * c_old;
             // let L = sG + cpk_j;
-            let L = RistrettoPoint::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &self.s_vec[j]);
+            let L = C::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &self.s_vec[j]);
 
-            tohash_vec[idx..].copy_from_slice(L.compress().as_bytes());
-            c_old = Scalar::hash_from_bytes::<Keccak512>(&tohash_vec);
-            j = j + 1;
+            c_old = RingSig::<C>::challenge(&transcript, &L);
+            j += 1;
         }
 
         if c_old == self.c {
@@ -146,71 +249,140 @@ impl RingSig{
         }
     }
 
-    pub fn initialize(n: usize) -> RingSig {
+    pub fn to_bytes(&self) -> Vec<u8> {
+
+        // canonical encoding: ring size as a little-endian u32, s_vec, c
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.size_in_bytes());
+        bytes.extend_from_slice(&(self.s_vec.len() as u32).to_le_bytes());
+        for s_i in self.s_vec.iter() {
+            bytes.extend_from_slice(s_i.as_bytes());
+        }
+        bytes.extend_from_slice(self.c.as_bytes());
+        bytes
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        // length of to_bytes(), computed without building the encoding
+        LEN_BYTES + (self.s_vec.len() + 1) * SCALAR_BYTES
+    }
+
+    // Reads the encoding of to_bytes(), for a ring of size n
+    pub fn read_from<R: Read>(reader: &mut R, n: usize) -> Result<RingSig<C>, Errors> {
+        if read_len(reader)? != n {
+            return Err(MProveSigsError);
+        }
+        let s_vec = (0..n).map(|_| read_scalar(reader)).collect::<Result<Vec<Scalar>, Errors>>()?;
+        let c = read_scalar(reader)?;
+        Ok(RingSig {
+            s_vec,
+            c,
+            _curve: PhantomData,
+        })
+    }
+
+    // Decodes to_bytes() from untrusted input
+    pub fn from_bytes(bytes: &[u8]) -> Result<RingSig<C>, Errors> {
+        let n = checked_len(bytes, RingSig::<C>::encoded_len).ok_or(MProveSigsError)?;
+        RingSig::read_from(&mut &bytes[..], n)
+    }
+
+    // length of the encoding of a signature over n keys
+    pub fn encoded_len(n: usize) -> Option<usize> {
+        n.checked_add(1)?.checked_mul(SCALAR_BYTES)?.checked_add(LEN_BYTES)
+    }
 
-        // let _fg = ::flame::start_guard("initialize sig");
+    pub fn initialize(n: usize) -> RingSig<C> {
 
         // function to initialize a RingSig variable
         let s_vec = (0..n).map(|_| { Scalar::one()}).collect::<Vec<Scalar>>();
         let c = Scalar::one();
 
-        return RingSig {
+        RingSig {
             s_vec,
             c,
-        };
+            _curve: PhantomData,
+        }
     }
 }
 
 #[derive(Clone, Debug)]
-pub struct LSAGSig{
+pub struct LSAGSig<C: Curve = Ristretto>{
     s_vec: Vec<Scalar>,
     c: Scalar,
-    I: RistrettoPoint,
+    I: C::Point,
 }
 
-impl LSAGSig{
+impl<C: Curve> LSAGSig<C>{
     pub fn gen_LSAG(
-        message: RistrettoPoint,
-        pk: &[RistrettoPoint],
+        message: C::Point,
+        pk: &[C::Point],
         x: Scalar,
         index: usize,
-    ) -> LSAGSig {
+    ) -> LSAGSig<C> {
+        LSAGSig::gen_LSAG_with_rng(message, pk, x, index, &mut rand::thread_rng())
+    }
 
-        // let _fg = ::flame::start_guard("gen LSAG");
+    // Same as gen_LSAG, drawing alpha and the s_i from rng
+    pub fn gen_LSAG_with_rng<R: RngCore + CryptoRng>(
+        message: C::Point,
+        pk: &[C::Point],
+        x: Scalar,
+        index: usize,
+        rng: &mut R,
+    ) -> LSAGSig<C> {
 
-        // ring size
-        let n = pk.len();
-        let mut rng = rand::thread_rng();
-        assert!(n >= 2, "Error! Why ring signature if cols = 1!");
-        assert!(index < n, "Index out of range");
+        profile_span!("gen LSAG");
+
+        assert!(index < pk.len(), "Index out of range");
 
-        // let _fgI = ::flame::start_guard("I");
         // compute key-image
-        let H_P_idx = RistrettoPoint::hash_from_bytes::<Keccak512>(pk[index].compress().as_bytes());
-        let I = H_P_idx * x;
-        // _fgI.end();
+        let (H_P_idx, I) = {
+            profile_span!("I");
+            let H_P_idx = C::key_image_base(&pk[index]);
+            (H_P_idx, H_P_idx * x)
+        };
 
-        // Pick alpha and s_i \in {1,2,...,q-1}, i = 0,1,...,n-1
-        let alpha = Scalar::random(&mut rng);
-        let mut s_vec: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        // Pick alpha
+        let alpha = Scalar::random(rng);
 
-        // let _fgLR = ::flame::start_guard("L,R");
         // Compute L_j and R_j
-        let G = constants::RISTRETTO_BASEPOINT_POINT;
-        let L_j = G * alpha;
-        let R_j = H_P_idx * alpha;
-        // _fgLR.end();
-
-        // Construct a vector of EC points to hash
-        let mut tohash_vec: Vec<u8> = Vec::new();
-        for i in 0..n {
-            tohash_vec.extend_from_slice(pk[i].compress().as_bytes());
-        }
-        tohash_vec.extend_from_slice(message.compress().as_bytes());
-        tohash_vec.extend_from_slice(L_j.compress().as_bytes());
-        tohash_vec.extend_from_slice(R_j.compress().as_bytes());
+        let G = C::basepoint();
+        let (L_j, R_j) = {
+            profile_span!("L,R");
+            (G * alpha, H_P_idx * alpha)
+        };
 
-        let mut c_old = Scalar::hash_from_bytes::<Keccak512>(&tohash_vec);        
+        // compute s_j
+        let (mut sig, c_j) = LSAGSig::close_ring(message, pk, index, I, L_j, R_j, rng);
+        sig.s_vec[index] = alpha - (c_j * x);
+        sig
+    }
+
+    // Completes the ring from the signer's key image I and commitments
+    // L_index = alpha*G, R_index = alpha*H_p(pk[index]): picks the s_i of
+    // the other members and returns the signature, with s_index still to be
+    // set to alpha - c_index*x, and c_index.
+    pub(crate) fn close_ring<R: RngCore + CryptoRng>(
+        message: C::Point,
+        pk: &[C::Point],
+        index: usize,
+        I: C::Point,
+        L_j: C::Point,
+        R_j: C::Point,
+        rng: &mut R,
+    ) -> (LSAGSig<C>, Scalar) {
+
+        // ring size
+        let n = pk.len();
+        assert!(n >= 2, "Error! Why ring signature if cols = 1!");
+        assert!(index < n, "Index out of range");
+
+        // Pick s_i \in {1,2,...,q-1}, i = 0,1,...,n-1
+        let mut s_vec: Vec<_> = (0..n).map(|_| Scalar::random(rng)).collect();
+
+        // transcript of the ring, shared by every challenge
+        let transcript = LSAGSig::<C>::transcript(message, pk, &I);
+        let mut c_old = LSAGSig::<C>::challenge(&transcript, &L_j, &R_j);
 
         // compute c0
         let mut j = (index + 1)%n;
@@ -220,120 +392,171 @@ impl LSAGSig{
         }
 
         // computing (j mod n) points and integers
-        let idxL = (n+1)*32;
-        let idxR = (n+2)*32;
         while j != index {
 
             // compute L
-            // let _fgL = ::flame::start_guard("L");
-            s_vec[j] = Scalar::random(&mut rng);
+            s_vec[j] = Scalar::random(rng);
             // let sG = G * s_vec[j];
             // let cpk_j = pk[j] * c_old;
             // let L = sG + cpk_j;
-            let L = RistrettoPoint::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &s_vec[j]);
-            // _fgL.end();
+            let L = {
+                profile_span!("L");
+                C::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &s_vec[j])
+            };
 
-            // let _fg0 = ::flame::start_guard("htp");
-            let H_P = RistrettoPoint::hash_from_bytes::<Keccak512>(pk[j].compress().as_bytes());
-            // _fg0.end();
+            let H_P = {
+                profile_span!("htp");
+                C::key_image_base(&pk[j])
+            };
 
             // compute R
-            // let _fgR = ::flame::start_guard("R");
             // let sH = H_P * s_vec[j];
             // let cj_I = I * c_old;
             // let R = sH + cj_I;
-            let R = RistrettoPoint::vartime_multiscalar_mul(&[s_vec[j], c_old], &[H_P, I]);
-            // _fgR.end();
+            let R = {
+                profile_span!("R");
+                C::vartime_multiscalar_mul(&[s_vec[j], c_old], &[H_P, I])
+            };
 
             // compute next c
-            // let _fg1 = ::flame::start_guard("hts");
-            tohash_vec[idxL..idxR].copy_from_slice(L.compress().as_bytes());
-            tohash_vec[idxR..].copy_from_slice(R.compress().as_bytes());
-
-            c_old = Scalar::hash_from_bytes::<Keccak512>(&tohash_vec); 
+            c_old = {
+                profile_span!("hts");
+                LSAGSig::<C>::challenge(&transcript, &L, &R)
+            };
             j = (j + 1) % n;
-            // _fg1.end();
 
             if j == 0 {
                 c = c_old;
             }
         }
 
-        // compute s_j
-        s_vec[index] = alpha - (c_old * x);
-
-        return LSAGSig {
+        let sig = LSAGSig {
             s_vec,
             c,
             I,
         };
+        (sig, c_old)
+    }
+
+    // sets s_index of a signature from close_ring
+    pub(crate) fn set_response(&mut self, index: usize, s: Scalar) {
+        self.s_vec[index] = s;
+    }
+
+    // Recomputes c_index of a signature from close_ring, walking the ring
+    // on from the commitments L_index and R_index, which must meet c at
+    // position 0
+    pub(crate) fn challenge_at(
+        &self,
+        message: C::Point,
+        pk: &[C::Point],
+        index: usize,
+        L_j: C::Point,
+        R_j: C::Point,
+    ) -> Result<Scalar, Errors> {
+
+        let n = pk.len();
+        if n < 2 || index >= n || self.s_vec.len() != n {
+            return Err(MProveSigsError);
+        }
+
+        let transcript = LSAGSig::<C>::transcript(message, pk, &self.I);
+        let mut c_old = LSAGSig::<C>::challenge(&transcript, &L_j, &R_j);
+        let mut j = (index + 1) % n;
+        loop {
+            if j == 0 && c_old != self.c {
+                return Err(MProveSigsError);
+            }
+            if j == index {
+                return Ok(c_old);
+            }
+            let L = C::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &self.s_vec[j]);
+            let R = C::vartime_multiscalar_mul(&[self.s_vec[j], c_old], &[C::key_image_base(&pk[j]), self.I]);
+            c_old = LSAGSig::<C>::challenge(&transcript, &L, &R);
+            j = (j + 1) % n;
+        }
+    }
+
+    // ring size, ring, message and key image
+    fn transcript(message: C::Point, pk: &[C::Point], I: &C::Point) -> Transcript<C> {
+        let mut transcript = Transcript::new(b"LSAG");
+        transcript.append_points(b"pk", pk);
+        transcript.append_point(b"message", &message);
+        transcript.append_point(b"I", I);
+        transcript
+    }
+
+    // challenge following the member with commitments L and R
+    fn challenge(transcript: &Transcript<C>, L: &C::Point, R: &C::Point) -> Scalar {
+        let mut transcript = transcript.clone();
+        transcript.append_point(b"L", L);
+        transcript.append_point(b"R", R);
+        transcript.challenge_scalar(b"c")
     }
 
     pub fn ver_LSAG(
         &self,
-        message: RistrettoPoint,
-        pk: &[RistrettoPoint],
+        message: C::Point,
+        pk: &[C::Point],
     ) -> Result<(), Errors> {
 
-        // let _fg = ::flame::start_guard("ver LSAG");
+        profile_span!("ver LSAG");
         
-        // ring size
+        // ring size; a malformed signature is rejected, not a panic
         let n = pk.len();
-        // let G = constants::RISTRETTO_BASEPOINT_POINT;
-        assert!(n >= 2, "Error! Why ring signature if cols = 1!");
-        assert!(self.s_vec.len() == n, "Bad rs.s_vec size.");
+        if n < 2 || self.s_vec.len() != n {
+            return Err(MProveSigsError);
+        }
 
         // check if any element of s_vec or c is 0
-        for i in 0..n {
-            assert!(self.s_vec[i] != Scalar::zero(), "s_vec cannot have a 0 element!");
+        if self.c == Scalar::zero() || self.s_vec.iter().any(|s_i| *s_i == Scalar::zero()) {
+            return Err(MProveSigsError);
         }
-        assert!(self.c != Scalar::zero(), "c cannot be 0!");
 
-        // Construct a vector of EC points to hash
-        let mut tohash_vec: Vec<u8> = Vec::new();
-        for i in 0..n {
-            tohash_vec.extend_from_slice(pk[i].compress().as_bytes());
+        // the key image must be a non-trivial element of the prime-order
+        // subgroup, otherwise a torsion component could make it unlinkable
+        if self.I == C::identity() || !C::in_prime_order_subgroup(&self.I) {
+            return Err(MProveSigsError);
         }
-        tohash_vec.extend_from_slice(message.compress().as_bytes());
-        tohash_vec.extend_from_slice(message.compress().as_bytes());
-        tohash_vec.extend_from_slice(message.compress().as_bytes());
+
+        // transcript of the ring, shared by every challenge
+        let transcript = LSAGSig::<C>::transcript(message, pk, &self.I);
 
         // compute c_j's
         let mut j: usize = 0;
         let mut c_old = self.c;
-        let idxL = (n+1)*32;
-        let idxR = (n+2)*32;
         while j < n {
 
-            // let _fgL = ::flame::start_guard("L");
             // compute L
             // let sG = G * self.s_vec[j];
             // let cpk_j = pk[j] * c_old;
             // let L = sG + cpk_j;
-            let L = RistrettoPoint::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &self.s_vec[j]);
-            // _fgL.end();
+            let L = {
+                profile_span!("L");
+                C::vartime_double_scalar_mul_basepoint(&c_old, &pk[j], &self.s_vec[j])
+            };
 
             // compute Hash of pubkey
-            // let _fg0 = ::flame::start_guard("htp");
-            let H_P = RistrettoPoint::hash_from_bytes::<Keccak512>(pk[j].compress().as_bytes());
-            // _fg0.end();
+            let H_P = {
+                profile_span!("htp");
+                C::key_image_base(&pk[j])
+            };
 
             // compute R
-            // let _fgR = ::flame::start_guard("R");
             // let sH = H_P * self.s_vec[j];
             // let cj_I = self.I * c_old;
             // let R = sH + cj_I;
-            let R = RistrettoPoint::vartime_multiscalar_mul(&[self.s_vec[j], c_old], &[H_P, self.I]);
-            // _fgR.end();
-
-            tohash_vec[idxL..idxR].copy_from_slice(L.compress().as_bytes());
-            tohash_vec[idxR..].copy_from_slice(R.compress().as_bytes());
-          
-            // let _fg1 = ::flame::start_guard("hts");
-            c_old = Scalar::hash_from_bytes::<Keccak512>(&tohash_vec); 
-            // _fg1.end();
+            let R = {
+                profile_span!("R");
+                C::vartime_multiscalar_mul(&[self.s_vec[j], c_old], &[H_P, self.I])
+            };
+
+            c_old = {
+                profile_span!("hts");
+                LSAGSig::<C>::challenge(&transcript, &L, &R)
+            };
             
-            j = j + 1;
+            j += 1;
         }
 
         if c_old == self.c {
@@ -343,17 +566,62 @@ impl LSAGSig{
         }
     }
 
-    pub fn initialize(n: usize) -> LSAGSig {
+    pub fn to_bytes(&self) -> Vec<u8> {
+
+        // canonical encoding: ring size as a little-endian u32, s_vec, c, I
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.size_in_bytes());
+        bytes.extend_from_slice(&(self.s_vec.len() as u32).to_le_bytes());
+        for s_i in self.s_vec.iter() {
+            bytes.extend_from_slice(s_i.as_bytes());
+        }
+        bytes.extend_from_slice(self.c.as_bytes());
+        bytes.extend_from_slice(&C::compress(&self.I));
+        bytes
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        // length of to_bytes(), computed without building the encoding
+        LEN_BYTES + (self.s_vec.len() + 1) * SCALAR_BYTES + POINT_BYTES
+    }
+
+    // Reads the encoding of to_bytes(), for a ring of size n
+    pub fn read_from<R: Read>(reader: &mut R, n: usize) -> Result<LSAGSig<C>, Errors> {
+        if read_len(reader)? != n {
+            return Err(MProveSigsError);
+        }
+        let s_vec = (0..n).map(|_| read_scalar(reader)).collect::<Result<Vec<Scalar>, Errors>>()?;
+        let c = read_scalar(reader)?;
+        let I = read_point::<C, R>(reader)?;
+        Ok(LSAGSig { s_vec, c, I })
+    }
+
+    // Decodes to_bytes() from untrusted input
+    pub fn from_bytes(bytes: &[u8]) -> Result<LSAGSig<C>, Errors> {
+        let n = checked_len(bytes, LSAGSig::<C>::encoded_len).ok_or(MProveSigsError)?;
+        LSAGSig::read_from(&mut &bytes[..], n)
+    }
+
+    // length of the encoding of a signature over n keys
+    pub fn encoded_len(n: usize) -> Option<usize> {
+        RingSig::<C>::encoded_len(n)?.checked_add(POINT_BYTES)
+    }
+
+    // linking tag of the signer's key
+    pub fn key_image(&self) -> C::Point {
+        self.I
+    }
+
+    pub fn initialize(n: usize) -> LSAGSig<C> {
         // function to initialize a LSAGSig variable
         let s_vec = (0..n).map(|_| { Scalar::one() }).collect::<Vec<Scalar>>();
         let c = Scalar::one();
-        let I = constants::RISTRETTO_BASEPOINT_POINT;
+        let I = C::basepoint();
 
-        return LSAGSig {
+        LSAGSig {
             s_vec,
             c,
             I,
-        };
+        }
     }
 }
 
@@ -361,22 +629,26 @@ impl LSAGSig{
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proofs::backend::MoneroEd25519;
+    use proptest::prelude::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
     use std::time::{Instant};
 
-    pub fn test_RingSig(n: usize, idx: usize){
+    pub fn test_RingSig<C: Curve>(n: usize, idx: usize){
         
-        let G = constants::RISTRETTO_BASEPOINT_POINT;
+        let G = C::basepoint();
         let mut rng = rand::thread_rng();
-        let msg = RistrettoPoint::random(&mut rng);
+        let msg = C::random_point(&mut rng);
 
-        let mut pk_vec: Vec<RistrettoPoint> = (0..n).map(|_| RistrettoPoint::random(&mut rng)).collect();
+        let mut pk_vec: Vec<C::Point> = (0..n).map(|_| C::random_point(&mut rng)).collect();
         let x = Scalar::random(&mut rng);
         pk_vec[idx] = G * x;
 
         // RingSig test
         println!("RingSig: (n={}, idx={})", n, idx);
         let start = Instant::now();
-        let ring_sample = RingSig::gen_RingSig(msg, &pk_vec, x, idx); 
+        let ring_sample = RingSig::<C>::gen_RingSig(msg, &pk_vec, x, idx); 
         let duration = start.elapsed();
         println!("gen time: {:?}", duration);
 
@@ -390,7 +662,7 @@ mod tests {
         // LSAG test
         println!("LSAG: (n={}, idx={})", n, idx);
         let start = Instant::now();
-        let LSAGring_sample = LSAGSig::gen_LSAG(msg, &pk_vec, x, idx);
+        let LSAGring_sample = LSAGSig::<C>::gen_LSAG(msg, &pk_vec, x, idx);
         let duration = start.elapsed();
         println!("gen time: {:?}", duration);
 
@@ -400,6 +672,13 @@ mod tests {
         println!("ver time: {:?}", duration);
 
         assert!(LSAGresult.is_ok());
+        assert_eq!(LSAGring_sample.key_image(), C::key_image_base(&pk_vec[idx]) * x);
+
+        // encodings
+        assert_eq!(ring_sample.to_bytes().len(), 4 + (n + 1) * 32);
+        assert_eq!(ring_sample.size_in_bytes(), ring_sample.to_bytes().len());
+        assert_eq!(LSAGring_sample.to_bytes().len(), 4 + (n + 2) * 32);
+        assert_eq!(LSAGring_sample.size_in_bytes(), LSAGring_sample.to_bytes().len());
     }
 
     #[test]
@@ -411,12 +690,169 @@ mod tests {
         // LSAG: (n=1000, idx=100)
         // gen time: 4.445220886s
         // ver time: 4.558239365s
-        test_RingSig(1000, 100);
+        test_RingSig::<Ristretto>(1000, 100);
     }
-}
 
+    #[test]
+    pub fn sim_RingSig_monero(){
+        test_RingSig::<MoneroEd25519>(16, 5);
+    }
 
+    #[test]
+    #[should_panic(expected = "Index out of range")]
+    pub fn LSAG_index_out_of_range(){
+        let mut rng = rand::thread_rng();
+        let pk: Vec<_> = (0..3).map(|_| <Ristretto>::random_point(&mut rng)).collect();
+        LSAGSig::<Ristretto>::gen_LSAG(<Ristretto>::random_point(&mut rng), &pk, Scalar::one(), 3);
+    }
 
+    pub fn test_tampered_sigs<C: Curve>(n: usize, idx: usize){
+        let G = C::basepoint();
+        let mut rng = rand::thread_rng();
+        let msg = C::random_point(&mut rng);
 
+        let mut pk_vec: Vec<C::Point> = (0..n).map(|_| C::random_point(&mut rng)).collect();
+        let x = Scalar::random(&mut rng);
+        pk_vec[idx] = G * x;
 
+        let rs = RingSig::<C>::gen_RingSig(msg, &pk_vec, x, idx);
+        let lsag = LSAGSig::<C>::gen_LSAG(msg, &pk_vec, x, idx);
+        assert!(rs.ver_RingSig(msg, &pk_vec).is_ok());
+        assert!(lsag.ver_LSAG(msg, &pk_vec).is_ok());
+
+        // each s_i and c
+        for j in 0..n {
+            let mut bad_rs = rs.clone();
+            bad_rs.s_vec[j] += Scalar::one();
+            assert_eq!(bad_rs.ver_RingSig(msg, &pk_vec), Err(MProveSigsError));
+            let mut bad_lsag = lsag.clone();
+            bad_lsag.s_vec[j] += Scalar::one();
+            assert_eq!(bad_lsag.ver_LSAG(msg, &pk_vec), Err(MProveSigsError));
+        }
+        let mut bad_rs = rs.clone();
+        bad_rs.c += Scalar::one();
+        assert_eq!(bad_rs.ver_RingSig(msg, &pk_vec), Err(MProveSigsError));
+        let mut bad_lsag = lsag.clone();
+        bad_lsag.c += Scalar::one();
+        assert_eq!(bad_lsag.ver_LSAG(msg, &pk_vec), Err(MProveSigsError));
+
+        // zero scalars
+        let mut bad_rs = rs.clone();
+        bad_rs.s_vec[0] = Scalar::zero();
+        assert_eq!(bad_rs.ver_RingSig(msg, &pk_vec), Err(MProveSigsError));
+        let mut bad_lsag = lsag.clone();
+        bad_lsag.c = Scalar::zero();
+        assert_eq!(bad_lsag.ver_LSAG(msg, &pk_vec), Err(MProveSigsError));
+
+        // another message, or the ring in another order
+        let other_msg = C::random_point(&mut rng);
+        assert_eq!(rs.ver_RingSig(other_msg, &pk_vec), Err(MProveSigsError));
+        assert_eq!(lsag.ver_LSAG(other_msg, &pk_vec), Err(MProveSigsError));
+        let mut swapped = pk_vec.clone();
+        swapped.swap(0, n - 1);
+        assert_eq!(rs.ver_RingSig(msg, &swapped), Err(MProveSigsError));
+        assert_eq!(lsag.ver_LSAG(msg, &swapped), Err(MProveSigsError));
+
+        // replaced key image
+        let mut bad_lsag = lsag.clone();
+        bad_lsag.I = C::random_point(&mut rng);
+        assert_eq!(bad_lsag.ver_LSAG(msg, &pk_vec), Err(MProveSigsError));
+        bad_lsag.I = C::identity();
+        assert_eq!(bad_lsag.ver_LSAG(msg, &pk_vec), Err(MProveSigsError));
+
+        // truncated signatures and rings
+        let mut bad_rs = rs.clone();
+        bad_rs.s_vec.pop();
+        assert_eq!(bad_rs.ver_RingSig(msg, &pk_vec), Err(MProveSigsError));
+        let mut bad_lsag = lsag.clone();
+        bad_lsag.s_vec.pop();
+        assert_eq!(bad_lsag.ver_LSAG(msg, &pk_vec), Err(MProveSigsError));
+        assert_eq!(rs.ver_RingSig(msg, &pk_vec[..n - 1]), Err(MProveSigsError));
+        assert_eq!(lsag.ver_LSAG(msg, &pk_vec[..1]), Err(MProveSigsError));
+
+        // wrong secret key
+        let wrong_x = x + Scalar::one();
+        let bad_rs = RingSig::<C>::gen_RingSig(msg, &pk_vec, wrong_x, idx);
+        assert_eq!(bad_rs.ver_RingSig(msg, &pk_vec), Err(MProveSigsError));
+        let bad_lsag = LSAGSig::<C>::gen_LSAG(msg, &pk_vec, wrong_x, idx);
+        assert_eq!(bad_lsag.ver_LSAG(msg, &pk_vec), Err(MProveSigsError));
+    }
+
+    #[test]
+    pub fn tampered_sigs(){
+        test_tampered_sigs::<Ristretto>(5, 2);
+        test_tampered_sigs::<Ristretto>(2, 0);
+        test_tampered_sigs::<MoneroEd25519>(4, 3);
+    }
 
+    // ring of n random keys with x*G at idx, and a message, drawn from seed
+    fn random_ring<C: Curve>(n: usize, idx: usize, x: &Scalar, seed: u64) -> (Vec<C::Point>, C::Point) {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let mut pk_vec: Vec<C::Point> = (0..n).map(|_| C::random_point(&mut rng)).collect();
+        pk_vec[idx] = C::basepoint() * *x;
+        (pk_vec, C::random_point(&mut rng))
+    }
+
+    pub fn check_round_trip<C: Curve>(n: usize, idx: usize, seed: u64){
+        let x = Scalar::from(seed) + Scalar::one();
+        let (pk_vec, msg) = random_ring::<C>(n, idx, &x, seed);
+        let rs = RingSig::<C>::gen_RingSig(msg, &pk_vec, x, idx);
+        let lsag = LSAGSig::<C>::gen_LSAG(msg, &pk_vec, x, idx);
+        assert!(rs.ver_RingSig(msg, &pk_vec).is_ok());
+        assert!(lsag.ver_LSAG(msg, &pk_vec).is_ok());
+
+        // another message
+        let other_msg = msg + C::basepoint();
+        assert_eq!(rs.ver_RingSig(other_msg, &pk_vec), Err(MProveSigsError));
+        assert_eq!(lsag.ver_LSAG(other_msg, &pk_vec), Err(MProveSigsError));
+
+        // another ring: one other member replaced
+        let mut other_ring = pk_vec.clone();
+        let j = (idx + 1) % n;
+        other_ring[j] = other_ring[j] + C::basepoint();
+        assert_eq!(rs.ver_RingSig(msg, &other_ring), Err(MProveSigsError));
+        assert_eq!(lsag.ver_LSAG(msg, &other_ring), Err(MProveSigsError));
+    }
+
+    // ring sizes from 2, now and then a large one, with the signer at
+    // either end or anywhere in between
+    fn ring_and_index(max: usize) -> impl Strategy<Value = (usize, usize)> {
+        prop_oneof![8 => 2..16usize, 1 => 64..max]
+            .prop_flat_map(|n| (Just(n), prop_oneof![Just(0), Just(n - 1), 0..n]))
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(24))]
+
+        #[test]
+        fn prop_round_trip((n, idx) in ring_and_index(160), seed in any::<u64>()) {
+            check_round_trip::<Ristretto>(n, idx, seed);
+        }
+
+        #[test]
+        fn prop_round_trip_monero((n, idx) in ring_and_index(80), seed in any::<u64>()) {
+            check_round_trip::<MoneroEd25519>(n, idx, seed);
+        }
+
+        #[test]
+        fn prop_key_image_determinism(
+            (n, idx) in ring_and_index(80),
+            (m, jdx) in ring_and_index(80),
+            seed in any::<u64>(),
+        ) {
+            // the same key in two unrelated rings
+            let x = Scalar::from(seed) + Scalar::one();
+            let (ring, msg) = random_ring::<Ristretto>(n, idx, &x, seed);
+            let (other_ring, other_msg) = random_ring::<Ristretto>(m, jdx, &x, seed.wrapping_add(1));
+            let I = LSAGSig::<Ristretto>::gen_LSAG(msg, &ring, x, idx).key_image();
+            let other_I = LSAGSig::<Ristretto>::gen_LSAG(other_msg, &other_ring, x, jdx).key_image();
+            prop_assert_eq!(I, other_I);
+            prop_assert_eq!(I, <Ristretto>::key_image_base(&ring[idx]) * x);
+
+            // and a different key
+            let y = x + Scalar::one();
+            let (ring, msg) = random_ring::<Ristretto>(n, idx, &y, seed);
+            prop_assert_ne!(I, LSAGSig::<Ristretto>::gen_LSAG(msg, &ring, y, idx).key_image());
+        }
+    }
+}
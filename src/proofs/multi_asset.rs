@@ -0,0 +1,284 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// MProve for confidential-asset chains, where each output commitment is
+// C_i = r_i*G + a_i*H_t for the generator H_t of the output's asset t.
+//
+// The proof runs one MProve per asset over the whole anonymity set, with H
+// replaced by H_t. Asset t's proof treats only the owned outputs of asset t
+// as owned, so C_res_t = y_t*G - a_t*H_t commits to the reserves in that
+// asset, and by ring signature anonymity no proof says which owned output
+// it counted. An output cannot be counted under an asset it does not carry,
+// since C_res_t would then not open to a multiple of H_t. Counting an
+// output under two assets (possible for zero amounts) is caught by
+// requiring the key images of all per-asset proofs to be distinct.
+use Errors::{self, MProveError, MProveWitnessError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::generators::Generators;
+use proofs::mprove::{MProveProof, MProveStatement, MProveWitness, OwnedOutput};
+use proofs::opening::CResOpening;
+
+use std::collections::HashSet;
+
+// Generator of an asset, derived from its label in the standard set
+pub fn asset_generator<C: Curve>(label: &[u8]) -> C::Point {
+    Generators::<C>::standard().asset(label)
+}
+
+// Public statement: like MProveStatement, with one amount generator per
+// asset. The asset of an output is not part of the statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiAssetStatement<C: Curve = Ristretto> {
+    pub G: C::Point,
+    pub H_vec: Vec<C::Point>,
+    pub C_vec: Vec<C::Point>,
+    pub P_vec: Vec<C::Point>,
+    pub context: Vec<u8>,
+}
+
+impl<C: Curve> MultiAssetStatement<C> {
+    pub fn new(
+        G: C::Point,
+        H_vec: Vec<C::Point>,
+        C_vec: Vec<C::Point>,
+        P_vec: Vec<C::Point>,
+        context: &[u8],
+    ) -> Result<MultiAssetStatement<C>, Errors> {
+
+        // asset generators must be distinct
+        let distinct: HashSet<[u8; 32]> = H_vec.iter().map(|H| C::compress(H)).collect();
+        if H_vec.is_empty() || distinct.len() != H_vec.len() {
+            return Err(MProveError);
+        }
+        if C_vec.len() != P_vec.len() || P_vec.is_empty() {
+            return Err(MProveError);
+        }
+
+        Ok(MultiAssetStatement {
+            G,
+            H_vec,
+            C_vec,
+            P_vec,
+            context: context.to_vec(),
+        })
+    }
+
+    pub fn num_assets(&self) -> usize {
+        self.H_vec.len()
+    }
+
+    // the single-asset statement proved for asset t
+    pub fn asset_statement(&self, t: usize) -> MProveStatement<C> {
+        MProveStatement {
+            G: self.G,
+            H: self.H_vec[t],
+            generators: None,
+            C_vec: self.C_vec.clone(),
+            P_vec: self.P_vec.clone(),
+            context: self.context.clone(),
+            identity: None,
+        }
+    }
+}
+
+// An exchange-owned output together with the index of its asset
+#[derive(Clone)]
+pub struct AssetOutput {
+    pub output: OwnedOutput,
+    pub asset: usize,
+}
+
+// Secret witness: the exchange-owned outputs of every asset, sorted by
+// position
+#[derive(Clone)]
+pub struct MultiAssetWitness {
+    owned: Vec<AssetOutput>,
+}
+
+impl MultiAssetWitness {
+    pub fn new(owned: Vec<AssetOutput>) -> Result<MultiAssetWitness, Errors> {
+        for pair in owned.windows(2) {
+            if pair[0].output.index >= pair[1].output.index {
+                return Err(MProveWitnessError);
+            }
+        }
+        Ok(MultiAssetWitness { owned })
+    }
+
+    pub fn owned(&self) -> &[AssetOutput] {
+        &self.owned
+    }
+
+    // witness of the single-asset proof for asset t
+    pub fn asset_witness(&self, t: usize) -> MProveWitness {
+        let owned = self
+            .owned
+            .iter()
+            .filter(|out| out.asset == t)
+            .map(|out| out.output.clone())
+            .collect();
+        MProveWitness::new(owned).expect("positions are sorted")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiAssetProof<C: Curve = Ristretto> {
+    proofs: Vec<MProveProof<C>>,
+}
+
+impl<C: Curve> MultiAssetProof<C> {
+    pub fn prove(
+        stmt: &MultiAssetStatement<C>,
+        witness: &MultiAssetWitness,
+    ) -> Result<MultiAssetProof<C>, Errors> {
+        MultiAssetProof::prove_with_openings(stmt, witness).map(|(proof, _)| proof)
+    }
+
+    // Same as prove, but also returns the opening of every C_res_t
+    pub fn prove_with_openings(
+        stmt: &MultiAssetStatement<C>,
+        witness: &MultiAssetWitness,
+    ) -> Result<(MultiAssetProof<C>, Vec<CResOpening>), Errors> {
+
+        if witness.owned().iter().any(|out| out.asset >= stmt.num_assets()) {
+            return Err(MProveWitnessError);
+        }
+
+        let (proofs, openings) = (0..stmt.num_assets())
+            .map(|t| MProveProof::prove_with_opening(&stmt.asset_statement(t), &witness.asset_witness(t)))
+            .collect::<Result<Vec<(MProveProof<C>, CResOpening)>, Errors>>()?
+            .into_iter()
+            .unzip();
+        Ok((MultiAssetProof { proofs }, openings))
+    }
+
+    pub fn verify(&self, stmt: &MultiAssetStatement<C>) -> Result<(), Errors> {
+        if self.proofs.len() != stmt.num_assets() {
+            return Err(MProveError);
+        }
+
+        let mut key_images: HashSet<[u8; 32]> = HashSet::new();
+        for (t, proof) in self.proofs.iter().enumerate() {
+            proof.verify(&stmt.asset_statement(t))?;
+
+            // no output may be counted twice
+            for I in proof.key_images() {
+                if !key_images.insert(C::compress(&I)) {
+                    return Err(MProveError);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // reserve commitment of every asset, C_res_t = y_t*G - a_t*H_t
+    pub fn C_res_vec(&self) -> Vec<C::Point> {
+        self.proofs.iter().map(|proof| proof.C_res()).collect()
+    }
+
+    // the single-asset proof for asset t, e.g. to open its C_res
+    pub fn asset_proof(&self, t: usize) -> &MProveProof<C> {
+        &self.proofs[t]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::Rng;
+
+    // n outputs over the given assets, with owned outputs at `owned`
+    // positions carrying assets `owned_assets`
+    pub fn test_multi_asset(
+        n: usize,
+        labels: &[&[u8]],
+        owned: &[usize],
+        owned_assets: &[usize],
+    ) -> (MultiAssetStatement<Ristretto>, MultiAssetWitness) {
+        let mut rng = rand::thread_rng();
+        let G = <Ristretto>::basepoint();
+        let H_vec: Vec<_> = labels.iter().map(|label| asset_generator::<Ristretto>(label)).collect();
+        let mut C_vec: Vec<_> = (0..n).map(|_| <Ristretto>::random_point(&mut rng)).collect();
+        let mut P_vec: Vec<_> = (0..n).map(|_| <Ristretto>::random_point(&mut rng)).collect();
+
+        let owned = owned
+            .iter()
+            .zip(owned_assets.iter())
+            .map(|(&index, &asset)| {
+                let x = Scalar::random(&mut rng);
+                let amount = u64::from(rng.gen::<u32>());
+                let blinding = Scalar::random(&mut rng);
+                P_vec[index] = G * x;
+                C_vec[index] = G * blinding + H_vec[asset] * Scalar::from(amount);
+                AssetOutput {
+                    output: OwnedOutput { index, x, amount, blinding },
+                    asset,
+                }
+            })
+            .collect();
+
+        let stmt = MultiAssetStatement::new(G, H_vec, C_vec, P_vec, b"multi asset").unwrap();
+        (stmt, MultiAssetWitness::new(owned).unwrap())
+    }
+
+    #[test]
+    pub fn sim_multi_asset(){
+        let labels: [&[u8]; 3] = [b"XMR", b"USD", b"EUR"];
+        let (stmt, witness) = test_multi_asset(12, &labels, &[1, 4, 5, 9], &[2, 0, 2, 1]);
+
+        let (proof, openings) = MultiAssetProof::prove_with_openings(&stmt, &witness).unwrap();
+        assert!(proof.verify(&stmt).is_ok());
+        assert_eq!(proof.C_res_vec().len(), 3);
+
+        // each C_res_t opens to the total of asset t
+        for (t, opening) in openings.iter().enumerate() {
+            let total: u64 = witness
+                .owned()
+                .iter()
+                .filter(|out| out.asset == t)
+                .map(|out| out.output.amount)
+                .sum();
+            assert_eq!(opening.amount, total);
+            assert!(opening.verify(&stmt.asset_statement(t), proof.asset_proof(t)).is_ok());
+        }
+
+        // an output cannot be claimed under an asset it does not carry
+        let mut owned = witness.owned().to_vec();
+        owned[1].asset = 1;
+        let bad_witness = MultiAssetWitness::new(owned).unwrap();
+        assert_eq!(MultiAssetProof::prove(&stmt, &bad_witness).err(), Some(MProveWitnessError));
+
+        // duplicate asset generators
+        let H_vec = vec![stmt.H_vec[0], stmt.H_vec[0]];
+        assert!(MultiAssetStatement::<Ristretto>::new(stmt.G, H_vec, stmt.C_vec.clone(), stmt.P_vec.clone(), b"").is_err());
+    }
+
+    #[test]
+    pub fn double_counting(){
+        // a zero-amount output opens under every asset generator
+        let labels: [&[u8]; 2] = [b"XMR", b"USD"];
+        let (mut stmt, witness) = test_multi_asset(6, &labels, &[2], &[0]);
+        let mut zero = witness.owned()[0].output.clone();
+        zero.amount = 0;
+        stmt.C_vec[2] = stmt.G * zero.blinding;
+
+        let counted_twice = MultiAssetProof::<Ristretto> {
+            proofs: (0..2)
+                .map(|t| {
+                    let asset_witness = MProveWitness::new(vec![zero.clone()]).unwrap();
+                    MProveProof::prove(&stmt.asset_statement(t), &asset_witness).unwrap()
+                })
+                .collect(),
+        };
+        assert_eq!(counted_twice.verify(&stmt), Err(MProveError));
+    }
+}
@@ -0,0 +1,191 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Openings of the reserves commitment C_res of an MProve proof.
+//
+// With z_i the randomness the prover used for C_prime_i, and (a_i, r_i)
+// the amounts and blinding factors of the owned outputs,
+//     C_res = sum(C_prime_i - C_i) = y*G - a*H,
+// where a = sum(a_i) and y = sum(z_i) - sum(r_i). An auditor can either be
+// given (a, y) directly, or be convinced that C_res + a*H is a multiple of
+// G with a Schnorr proof, without learning y.
+use Errors::{self, MProveOpeningError};
+use proofs::backend::Curve;
+use proofs::mprove::{MProveProof, MProveStatement};
+use proofs::mprove_sigs::read_scalar;
+use proofs::transcript::Transcript;
+
+use curve25519_dalek::scalar::Scalar;
+
+// Full opening of C_res: the total amount and the aggregate blinding factor
+#[derive(Clone, Debug, PartialEq)]
+pub struct CResOpening {
+    pub amount: u64,
+    pub blinding: Scalar,
+}
+
+impl CResOpening {
+    pub fn verify<C: Curve>(
+        &self,
+        stmt: &MProveStatement<C>,
+        proof: &MProveProof<C>,
+    ) -> Result<(), Errors> {
+
+        if proof.C_res() + stmt.H * Scalar::from(self.amount) == stmt.G * self.blinding {
+            Ok(())
+        } else {
+            Err(MProveOpeningError)
+        }
+    }
+
+    // Zero-knowledge proof that C_res commits to self.amount
+    pub fn prove_amount<C: Curve>(
+        &self,
+        stmt: &MProveStatement<C>,
+        proof: &MProveProof<C>,
+    ) -> AmountProof {
+
+        let mut rng = rand::thread_rng();
+        let k = Scalar::random(&mut rng);
+        let R = stmt.G * k;
+
+        let c = AmountProof::challenge(stmt, proof, self.amount, &R);
+        let s = k - c * self.blinding;
+
+        AmountProof {
+            amount: self.amount,
+            c,
+            s,
+        }
+    }
+}
+
+pub const AMOUNT_PROOF_BYTES: usize = 64;
+
+// Schnorr proof of knowledge of y with C_res + amount*H = y*G. It is
+// encoded as c | s; the amount is stated alongside it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmountProof {
+    amount: u64,
+    c: Scalar,
+    s: Scalar,
+}
+
+impl AmountProof {
+    pub fn verify<C: Curve>(
+        &self,
+        stmt: &MProveStatement<C>,
+        proof: &MProveProof<C>,
+    ) -> Result<(), Errors> {
+
+        // R = s*G + c*(C_res + amount*H)
+        let Y = proof.C_res() + stmt.H * Scalar::from(self.amount);
+        let R = C::vartime_multiscalar_mul(&[self.s, self.c], &[stmt.G, Y]);
+
+        if AmountProof::challenge(stmt, proof, self.amount, &R) == self.c {
+            Ok(())
+        } else {
+            Err(MProveOpeningError)
+        }
+    }
+
+    // the publicly stated total
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    pub fn to_bytes(&self) -> [u8; AMOUNT_PROOF_BYTES] {
+        let mut bytes = [0u8; AMOUNT_PROOF_BYTES];
+        bytes[..32].copy_from_slice(self.c.as_bytes());
+        bytes[32..].copy_from_slice(self.s.as_bytes());
+        bytes
+    }
+
+    // Decodes to_bytes() of a proof of the stated amount, rejecting
+    // non-canonical scalars
+    pub fn from_bytes(amount: u64, bytes: &[u8]) -> Result<AmountProof, Errors> {
+        if bytes.len() != AMOUNT_PROOF_BYTES {
+            return Err(MProveOpeningError);
+        }
+        let mut reader = bytes;
+        Ok(AmountProof {
+            amount,
+            c: read_scalar(&mut reader).map_err(|_| MProveOpeningError)?,
+            s: read_scalar(&mut reader).map_err(|_| MProveOpeningError)?,
+        })
+    }
+
+    fn challenge<C: Curve>(
+        stmt: &MProveStatement<C>,
+        proof: &MProveProof<C>,
+        amount: u64,
+        R: &C::Point,
+    ) -> Scalar {
+
+        let mut transcript = Transcript::<C>::new(b"MProve amount");
+        transcript.append_point(b"message", &stmt.message());
+        transcript.append_point(b"C_res", &proof.C_res());
+        transcript.append_u64(b"amount", amount);
+        transcript.append_point(b"R", R);
+        transcript.challenge_scalar(b"c")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::backend::{MoneroEd25519, Ristretto};
+    use proofs::fixtures::Fixture;
+
+    pub fn test_opening<C: Curve>(){
+        let Fixture { stmt, witness } = Fixture::<C>::generate(8, 3, 0).unwrap();
+        let (proof, opening) = MProveProof::prove_with_opening(&stmt, &witness).unwrap();
+        assert!(proof.verify(&stmt).is_ok());
+        assert_eq!(opening.amount, witness.total_amount().unwrap());
+
+        // full opening
+        assert!(opening.verify(&stmt, &proof).is_ok());
+        let wrong_amount = CResOpening { amount: opening.amount + 1, ..opening.clone() };
+        assert_eq!(wrong_amount.verify(&stmt, &proof), Err(MProveOpeningError));
+
+        // zero-knowledge opening
+        let amount_proof = opening.prove_amount(&stmt, &proof);
+        assert_eq!(amount_proof.amount(), opening.amount);
+        assert!(amount_proof.verify(&stmt, &proof).is_ok());
+
+        // the encoding round-trips, for the stated amount only
+        let bytes = amount_proof.to_bytes();
+        let decoded = AmountProof::from_bytes(opening.amount, &bytes).unwrap();
+        assert_eq!(decoded, amount_proof);
+        assert!(decoded.verify(&stmt, &proof).is_ok());
+        let restated = AmountProof::from_bytes(opening.amount + 1, &bytes).unwrap();
+        assert_eq!(restated.verify(&stmt, &proof), Err(MProveOpeningError));
+        assert_eq!(AmountProof::from_bytes(opening.amount, &bytes[1..]), Err(MProveOpeningError));
+        let mut non_canonical = bytes;
+        non_canonical[63] = 0xff;
+        assert_eq!(AmountProof::from_bytes(opening.amount, &non_canonical), Err(MProveOpeningError));
+
+        let forged = AmountProof { amount: amount_proof.amount + 1, ..amount_proof.clone() };
+        assert_eq!(forged.verify(&stmt, &proof), Err(MProveOpeningError));
+
+        // a proof of the right amount does not transfer to another MProve proof
+        let other_proof = MProveProof::prove(&stmt, &witness).unwrap();
+        assert_eq!(amount_proof.verify(&stmt, &other_proof), Err(MProveOpeningError));
+        let wrong_blinding = wrong_amount.prove_amount(&stmt, &proof);
+        assert_eq!(wrong_blinding.verify(&stmt, &proof), Err(MProveOpeningError));
+    }
+
+    #[test]
+    pub fn open_C_res(){
+        test_opening::<Ristretto>();
+        test_opening::<MoneroEd25519>();
+    }
+}
@@ -0,0 +1,267 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Signed audit reports of verified MProve proofs.
+//
+// A report is only made from a proof that verifies against its statement.
+// It records what was verified: the statement digest, the anonymity set
+// parameters, C_res, a digest of the key images, the identity key of the
+// exchange claiming the proof, if any, when it was verified and by which
+// version of the library. The number of exchange-owned outputs is not
+// known to the verifier and is not recorded. The verifier signs the
+// transcript of all of these with a Schnorr signature, so the JSON
+// rendering can be passed around and checked without trusting its
+// formatting. Anyone holding the statement and proof can also check that a
+// report describes them.
+use Errors::{self, MProveError};
+use proofs::export::decode_point;
+use proofs::backend::Curve;
+use proofs::mprove::{MProveProof, MProveStatement};
+use proofs::schnorr::SchnorrSig;
+use proofs::transcript::Transcript;
+
+use curve25519_dalek::scalar::Scalar;
+
+// format of the report, changed with any change to its fields
+pub const REPORT_VERSION: &str = "MProve audit report v1";
+
+// Schnorr signing domain of reports
+const REPORT_DOMAIN: &[u8] = b"MProve audit report";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AnonymitySet {
+    pub size: usize,
+    // name of the generator set, if any, for display only; the statement
+    // digest binds the exact name
+    pub generators: Option<String>,
+    pub context: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditReport {
+    pub version: String,
+    pub suite: String,
+    pub statement_digest: String,
+    pub anonymity_set: AnonymitySet,
+    pub C_res: String,
+    pub key_image_digest: String,
+    pub exchange_identity: Option<String>,
+    // unix time, in seconds
+    pub verified_at: u64,
+    pub verifier_version: String,
+}
+
+// A report with the verifier's public key and signature, both hex
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SignedAuditReport {
+    pub report: AuditReport,
+    pub verifier_key: String,
+    pub signature: String,
+}
+
+impl AuditReport {
+    // Verifies the proof, as claimed by `identity` if given, and describes
+    // it. `suite` names the curve backend of C, as mprove_bin does. The
+    // statement digest is that of the statement claimed by the proof.
+    pub fn new<C: Curve>(
+        stmt: &MProveStatement<C>,
+        proof: &MProveProof<C>,
+        identity: Option<&C::Point>,
+        suite: &str,
+        verified_at: u64,
+    ) -> Result<AuditReport, Errors> {
+
+        proof.verify_with_identity(stmt, identity)?;
+        let statement_digest = match proof.identity() {
+            Some(P) if stmt.identity.is_none() => stmt.claimed_by(P).digest(),
+            _ => stmt.digest(),
+        };
+
+        Ok(AuditReport {
+            version: REPORT_VERSION.to_string(),
+            suite: suite.to_string(),
+            statement_digest: hex::encode(statement_digest),
+            anonymity_set: AnonymitySet {
+                size: stmt.len(),
+                generators: stmt.generators.as_ref().map(|name| String::from_utf8_lossy(name).into_owned()),
+                context: hex::encode(&stmt.context),
+            },
+            C_res: hex::encode(C::compress(&proof.C_res())),
+            key_image_digest: hex::encode(key_image_digest::<C>(&proof.key_images())),
+            exchange_identity: proof.identity().map(|P| hex::encode(C::compress(&P))),
+            verified_at,
+            verifier_version: env!("CARGO_PKG_VERSION").to_string(),
+        })
+    }
+
+    // Verifies the proof and checks that this report describes it, up to
+    // when and by which version it was verified
+    pub fn check<C: Curve>(&self, stmt: &MProveStatement<C>, proof: &MProveProof<C>) -> Result<(), Errors> {
+        let expected = AuditReport {
+            verifier_version: self.verifier_version.clone(),
+            ..AuditReport::new(stmt, proof, None, &self.suite, self.verified_at)?
+        };
+        if *self == expected {
+            Ok(())
+        } else {
+            Err(MProveError)
+        }
+    }
+
+    pub fn sign<C: Curve>(self, key: &Scalar) -> SignedAuditReport {
+        let signature = SchnorrSig::sign::<C>(key, REPORT_DOMAIN, &self.digest::<C>());
+        SignedAuditReport {
+            report: self,
+            verifier_key: hex::encode(C::compress(&(C::basepoint() * *key))),
+            signature: hex::encode(&signature.to_bytes()[..]),
+        }
+    }
+
+    // Hash of every field, which is what the verifier signs
+    pub fn digest<C: Curve>(&self) -> [u8; 32] {
+        let mut transcript = Transcript::<C>::new(REPORT_DOMAIN);
+        transcript.append_message(b"version", self.version.as_bytes());
+        transcript.append_message(b"suite", self.suite.as_bytes());
+        transcript.append_message(b"statement", self.statement_digest.as_bytes());
+        transcript.append_u64(b"size", self.anonymity_set.size as u64);
+        match self.anonymity_set.generators {
+            Some(ref name) => transcript.append_message(b"generators", name.as_bytes()),
+            None => transcript.append_message(b"no generators", b""),
+        }
+        transcript.append_message(b"context", self.anonymity_set.context.as_bytes());
+        transcript.append_message(b"C_res", self.C_res.as_bytes());
+        transcript.append_message(b"key images", self.key_image_digest.as_bytes());
+        match self.exchange_identity {
+            Some(ref P) => transcript.append_message(b"identity", P.as_bytes()),
+            None => transcript.append_message(b"no identity", b""),
+        }
+        transcript.append_u64(b"verified at", self.verified_at);
+        transcript.append_message(b"verifier version", self.verifier_version.as_bytes());
+        transcript.challenge_bytes(b"digest")
+    }
+}
+
+impl SignedAuditReport {
+    // Checks the signature against the key in the report. The caller should
+    // check that the key is the verifier it trusts, and that C is the
+    // backend named by the report's suite.
+    pub fn verify<C: Curve>(&self) -> Result<(), Errors> {
+        let P = decode_point::<C>(&self.verifier_key)?;
+        let signature = hex::decode(&self.signature).map_err(|_| MProveError)?;
+        SchnorrSig::from_bytes(&signature)?.verify::<C>(&P, REPORT_DOMAIN, &self.report.digest::<C>())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<SignedAuditReport, Errors> {
+        serde_json::from_str(text).map_err(|_| MProveError)
+    }
+
+    // Human-readable rendering, one field per line
+    pub fn summary(&self) -> String {
+        let report = &self.report;
+        let lines = vec![
+            report.version.clone(),
+            format!("Verified at       = {} (unix time)", report.verified_at),
+            format!("Verifier version  = {}", report.verifier_version),
+            format!("Curve             = {}", report.suite),
+            format!("Statement digest  = {}", report.statement_digest),
+            format!("Anonymity set     = {} outputs", report.anonymity_set.size),
+            format!(
+                "Generators        = {}",
+                report.anonymity_set.generators.as_ref().map(|name| &name[..]).unwrap_or("(unnamed)")
+            ),
+            format!("Context           = {}", report.anonymity_set.context),
+            format!("C_res             = {}", report.C_res),
+            format!("Key image digest  = {}", report.key_image_digest),
+            format!(
+                "Exchange identity = {}",
+                report.exchange_identity.as_ref().map(|P| &P[..]).unwrap_or("(unclaimed)")
+            ),
+            format!("Verifier key      = {}", self.verifier_key),
+            format!("Signature         = {}", self.signature),
+        ];
+        lines.join("\n")
+    }
+}
+
+// Digest of the key images of a proof, in position order
+pub fn key_image_digest<C: Curve>(key_images: &[C::Point]) -> [u8; 32] {
+    let mut transcript = Transcript::<C>::new(b"MProve key images");
+    transcript.append_points(b"I", key_images);
+    transcript.challenge_bytes(b"digest")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::backend::{MoneroEd25519, Ristretto};
+    use proofs::fixtures::Fixture;
+
+    pub fn test_report<C: Curve>(suite: &str){
+        let Fixture { stmt, witness } = Fixture::<C>::generate(6, 2, 0).unwrap();
+        let proof = MProveProof::prove(&stmt, &witness).unwrap();
+        let key = Scalar::random(&mut rand::thread_rng());
+
+        let report = AuditReport::new(&stmt, &proof, None, suite, 1_600_000_000).unwrap();
+        assert_eq!(report.anonymity_set.size, 6);
+        assert_eq!(report.exchange_identity, None);
+        assert_eq!(report.anonymity_set.generators, Some("standard".to_string()));
+        assert!(report.check(&stmt, &proof).is_ok());
+
+        let signed = report.clone().sign::<C>(&key);
+        assert!(signed.verify::<C>().is_ok());
+        let parsed = SignedAuditReport::from_json(&signed.to_json()).unwrap();
+        assert_eq!(parsed, signed);
+        assert!(parsed.verify::<C>().is_ok());
+        assert!(signed.summary().contains(&report.C_res));
+
+        // any edit breaks the signature
+        let mut edited = signed.clone();
+        edited.report.verified_at += 1;
+        assert!(edited.verify::<C>().is_err());
+        let mut edited = signed.clone();
+        edited.report.anonymity_set.size += 1;
+        assert!(edited.verify::<C>().is_err());
+        let mut edited = signed.clone();
+        edited.verifier_key = hex::encode(C::compress(&C::basepoint()));
+        assert!(edited.verify::<C>().is_err());
+
+        // no report of a proof that does not verify, or of another proof
+        let Fixture { stmt: other, witness: other_witness } = Fixture::<C>::generate(6, 2, 1).unwrap();
+        assert!(AuditReport::new(&other, &proof, None, suite, 0).is_err());
+        let other_proof = MProveProof::prove(&other, &other_witness).unwrap();
+        let other_report = AuditReport::new(&other, &other_proof, None, suite, 0).unwrap();
+        assert!(other_report.key_image_digest != report.key_image_digest);
+        assert_eq!(other_report.check(&stmt, &proof), Err(MProveError));
+
+        // a claimed proof names its exchange, and can be required to
+        let exchange = C::basepoint() * key;
+        let claimed_stmt = stmt.claimed_by(exchange);
+        let mut claimed = MProveProof::prove(&claimed_stmt, &witness).unwrap();
+        claimed.sign_identity(&claimed_stmt, &key).unwrap();
+        assert!(AuditReport::new(&stmt, &claimed, Some(&C::basepoint()), suite, 0).is_err());
+        let claimed_report = AuditReport::new(&stmt, &claimed, Some(&exchange), suite, 0).unwrap();
+        assert_eq!(claimed_report.exchange_identity, Some(hex::encode(C::compress(&exchange))));
+        assert!(claimed_report.check(&stmt, &claimed).is_ok());
+        assert_eq!(AuditReport::new(&claimed_stmt, &claimed, None, suite, 0), Ok(claimed_report.clone()));
+        assert_eq!(claimed_report.statement_digest, hex::encode(claimed_stmt.digest()));
+        assert_eq!(claimed_report.check(&stmt, &proof), Err(MProveError));
+    }
+
+    #[test]
+    pub fn audit_report(){
+        test_report::<Ristretto>("ristretto");
+        test_report::<MoneroEd25519>("monero");
+    }
+}
@@ -0,0 +1,288 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Strategies for choosing the anonymity set around the exchange's outputs.
+//
+// A sampler picks decoys from an output export, adds the exchange-owned
+// outputs and shuffles the result, so that the position of an output in
+// the statement says nothing about whether it is owned. Owned outputs must
+// themselves pass the sampler's filter, otherwise they would stand out from
+// the decoys.
+use Errors::{self, MProveMissingOutputError, MProveSamplerError};
+use proofs::anon_set::{OutputExport, OutputFilter};
+use proofs::backend::Curve;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_distr::{Distribution, Gamma};
+
+use std::collections::HashSet;
+
+pub trait AnonSetSampler<C: Curve> {
+    // Positions in `export` of the anonymity set, including every position
+    // in `owned`, in random order
+    fn sample<R: Rng>(
+        &self,
+        export: &OutputExport<C>,
+        owned: &[usize],
+        rng: &mut R,
+    ) -> Result<Vec<usize>, Errors>;
+}
+
+// n outputs, decoys drawn uniformly from those passing the filter
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniformSampler {
+    pub n: usize,
+    pub filter: OutputFilter,
+}
+
+// n outputs, decoy ages drawn like Monero's wallet does: the log of the age
+// in seconds follows a gamma distribution, and the age is turned into a
+// height using the block time.
+//
+// Only the decoys follow that distribution. The owned outputs are added at
+// whatever age they have, so one far older (or younger) than the decoys
+// drawn around it stands out by its age alone. Exchanges holding old
+// outputs should prefer the uniform or height-range samplers, or move the
+// funds to fresh outputs before an audit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GammaSampler {
+    pub n: usize,
+    pub filter: OutputFilter,
+    pub shape: f64,
+    pub scale: f64,
+    pub block_time: u64,
+}
+
+// Every output with min_height <= height <= max_height
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightRangeSampler {
+    pub min_height: u64,
+    pub max_height: u64,
+}
+
+impl GammaSampler {
+    // parameters of Monero's decoy selection (wallet2.cpp)
+    pub fn monero(n: usize, filter: OutputFilter) -> GammaSampler {
+        GammaSampler {
+            n,
+            filter,
+            shape: 19.28,
+            scale: 1.0 / 1.61,
+            block_time: 120,
+        }
+    }
+}
+
+impl<C: Curve> AnonSetSampler<C> for UniformSampler {
+    fn sample<R: Rng>(
+        &self,
+        export: &OutputExport<C>,
+        owned: &[usize],
+        rng: &mut R,
+    ) -> Result<Vec<usize>, Errors> {
+
+        let candidates = export.filter(&self.filter);
+        let owned_set = owned_positions(&candidates, owned, self.n)?;
+        let decoys: Vec<usize> = candidates
+            .into_iter()
+            .filter(|k| !owned_set.contains(k))
+            .collect();
+
+        let mut set: Vec<usize> = decoys
+            .choose_multiple(rng, self.n - owned.len())
+            .cloned()
+            .collect();
+        set.extend_from_slice(owned);
+        set.shuffle(rng);
+        Ok(set)
+    }
+}
+
+impl<C: Curve> AnonSetSampler<C> for GammaSampler {
+    fn sample<R: Rng>(
+        &self,
+        export: &OutputExport<C>,
+        owned: &[usize],
+        rng: &mut R,
+    ) -> Result<Vec<usize>, Errors> {
+
+        let mut candidates = export.filter(&self.filter);
+        let mut chosen = owned_positions(&candidates, owned, self.n)?;
+        if self.block_time == 0 {
+            return Err(MProveSamplerError);
+        }
+        let gamma = Gamma::new(self.shape, self.scale).map_err(|_| MProveSamplerError)?;
+        let tip = self
+            .filter
+            .tip_height
+            .or_else(|| export.tip_height())
+            .ok_or(MProveSamplerError)?;
+
+        // candidates from oldest to youngest, so that every draw is two
+        // binary searches, as in Monero's gamma_picker
+        let outputs = export.outputs();
+        candidates.sort_by_key(|&k| (outputs[k].height, k));
+
+        let mut set: Vec<usize> = owned.to_vec();
+        let mut attempts = 0;
+        while set.len() < self.n {
+            attempts += 1;
+            if attempts > 1000 * self.n {
+                return Err(MProveSamplerError);
+            }
+
+            // age in blocks, redrawn if older than the whole export
+            let age = gamma.sample(rng).exp() / self.block_time as f64;
+            if age >= tip as f64 {
+                continue;
+            }
+            let target = tip - age as u64;
+
+            // a random candidate from the youngest height at or below target
+            let last = candidates.partition_point(|&k| outputs[k].height <= target);
+            if last == 0 {
+                continue;
+            }
+            let height = outputs[candidates[last - 1]].height;
+            let first = candidates[..last].partition_point(|&k| outputs[k].height < height);
+            let k = candidates[rng.gen_range(first, last)];
+
+            if chosen.insert(k) {
+                set.push(k);
+            }
+        }
+        set.shuffle(rng);
+        Ok(set)
+    }
+}
+
+impl<C: Curve> AnonSetSampler<C> for HeightRangeSampler {
+    fn sample<R: Rng>(
+        &self,
+        export: &OutputExport<C>,
+        owned: &[usize],
+        rng: &mut R,
+    ) -> Result<Vec<usize>, Errors> {
+
+        let filter = OutputFilter {
+            min_height: Some(self.min_height),
+            max_height: Some(self.max_height),
+            ..OutputFilter::default()
+        };
+        let mut set = export.filter(&filter);
+        owned_positions(&set, owned, set.len())?;
+        set.shuffle(rng);
+        Ok(set)
+    }
+}
+
+// The owned positions as a set, after checking they are distinct, pass the
+// filter and fit in a set of size n
+fn owned_positions(candidates: &[usize], owned: &[usize], n: usize) -> Result<HashSet<usize>, Errors> {
+    let owned_set: HashSet<usize> = owned.iter().cloned().collect();
+    if owned_set.len() != owned.len() || owned.len() > n || n > candidates.len() {
+        return Err(MProveSamplerError);
+    }
+    let candidate_set: HashSet<usize> = candidates.iter().cloned().collect();
+    if !owned_set.is_subset(&candidate_set) {
+        return Err(MProveMissingOutputError);
+    }
+    Ok(owned_set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::anon_set::ExportedOutput;
+    use proofs::backend::MoneroEd25519;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    // one output per block at heights 0..len, with P = (k + 1)*G
+    pub fn test_export(len: usize) -> OutputExport<MoneroEd25519> {
+        let G = MoneroEd25519::basepoint();
+        let H = MoneroEd25519::amount_generator();
+        let mut P = MoneroEd25519::identity();
+        OutputExport::new(
+            (0..len)
+                .map(|k| {
+                    P += G;
+                    ExportedOutput { height: k as u64, P, C: P + H }
+                })
+                .collect(),
+        )
+    }
+
+    pub fn test_sampler<S: AnonSetSampler<MoneroEd25519>>(
+        sampler: &S,
+        export: &OutputExport<MoneroEd25519>,
+        owned: &[usize],
+        n: usize,
+    ){
+        let set = sampler.sample(export, owned, &mut ChaCha20Rng::from_seed([1u8; 32])).unwrap();
+        assert_eq!(set.len(), n);
+        assert!(owned.iter().all(|k| set.contains(k)));
+        assert_eq!(set.iter().collect::<HashSet<_>>().len(), n);
+
+        // reproducible from the seed, and shuffled
+        let again = sampler.sample(export, owned, &mut ChaCha20Rng::from_seed([1u8; 32])).unwrap();
+        assert_eq!(set, again);
+        assert!(set.windows(2).any(|pair| pair[0] > pair[1]));
+
+        let stmt = export.statement(&set, b"sampler").unwrap();
+        assert_eq!(stmt.len(), n);
+    }
+
+    #[test]
+    pub fn sample_anon_set(){
+        let export = test_export(20000);
+        let owned = [19000, 15000, 12345];
+        let filter = OutputFilter { min_age: 10, ..OutputFilter::default() };
+
+        test_sampler(&UniformSampler { n: 64, filter: filter.clone() }, &export, &owned, 64);
+        test_sampler(&GammaSampler::monero(64, filter.clone()), &export, &owned, 64);
+        test_sampler(&HeightRangeSampler { min_height: 12000, max_height: 19999 }, &export, &owned, 8000);
+
+        // owned outputs must pass the filter
+        let mut rng = rand::thread_rng();
+        let young = [19995];
+        let sampler = UniformSampler { n: 64, filter: filter.clone() };
+        assert_eq!(sampler.sample(&export, &young, &mut rng), Err(MProveMissingOutputError));
+        let sampler = HeightRangeSampler { min_height: 13000, max_height: 19999 };
+        assert_eq!(sampler.sample(&export, &owned, &mut rng), Err(MProveMissingOutputError));
+
+        // too many owned outputs, or not enough candidates
+        let sampler = UniformSampler { n: 2, filter: filter.clone() };
+        assert_eq!(sampler.sample(&export, &owned, &mut rng), Err(MProveSamplerError));
+        let sampler = GammaSampler::monero(20000, filter.clone());
+        assert_eq!(sampler.sample(&export, &owned, &mut rng), Err(MProveSamplerError));
+
+        // gamma decoys from blocks of several outputs, exported out of order
+        let mut outputs = export.outputs().to_vec();
+        for out in outputs.iter_mut() {
+            out.height /= 4;
+        }
+        outputs.reverse();
+        let export = OutputExport::new(outputs);
+        let set = GammaSampler::monero(64, filter.clone()).sample(&export, &[], &mut rng).unwrap();
+        assert_eq!(set.iter().collect::<HashSet<_>>().len(), 64);
+        assert!(set.iter().all(|&k| export.outputs()[k].height + 10 <= export.tip_height().unwrap()));
+
+        // owned outputs are not drawn by age: one from the first block is
+        // kept though no gamma decoy is that old, nor from its block
+        let oldest = export.outputs().iter().position(|out| out.height == 0).unwrap();
+        let sampler = GammaSampler::monero(64, filter);
+        let set = sampler.sample(&export, &[oldest], &mut rng).unwrap();
+        assert!(set.contains(&oldest));
+        assert!(set.iter().filter(|&&k| k != oldest).all(|&k| export.outputs()[k].height > 0));
+    }
+}
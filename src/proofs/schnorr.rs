@@ -0,0 +1,126 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Schnorr signatures under long-term keys P = x*G, with G the basepoint.
+//
+// A signature is (c, s) with R = s*G + c*P and c the challenge of a
+// transcript of the signing domain, P, the message and R. The domain keeps
+// a signature made for one purpose, say an audit report, from being
+// accepted for another. Encoded as c | s, 64 bytes.
+use Errors::{self, MProveSigsError};
+use proofs::backend::Curve;
+use proofs::mprove_sigs::read_scalar;
+use proofs::transcript::Transcript;
+
+use curve25519_dalek::scalar::Scalar;
+use rand::{CryptoRng, RngCore};
+
+pub const SCHNORR_BYTES: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchnorrSig {
+    c: Scalar,
+    s: Scalar,
+}
+
+impl SchnorrSig {
+    pub fn sign<C: Curve>(x: &Scalar, domain: &[u8], message: &[u8]) -> SchnorrSig {
+        SchnorrSig::sign_with_rng::<C, _>(x, domain, message, &mut rand::thread_rng())
+    }
+
+    pub fn sign_with_rng<C: Curve, R: RngCore + CryptoRng>(
+        x: &Scalar,
+        domain: &[u8],
+        message: &[u8],
+        rng: &mut R,
+    ) -> SchnorrSig {
+
+        let k = Scalar::random(rng);
+        let P = C::basepoint() * *x;
+        let R = C::basepoint() * k;
+
+        let c = SchnorrSig::challenge::<C>(domain, &P, message, &R);
+        SchnorrSig { c, s: k - c * *x }
+    }
+
+    pub fn verify<C: Curve>(&self, P: &C::Point, domain: &[u8], message: &[u8]) -> Result<(), Errors> {
+
+        // R = s*G + c*P
+        let R = C::vartime_double_scalar_mul_basepoint(&self.c, P, &self.s);
+
+        if self.c != Scalar::zero() && SchnorrSig::challenge::<C>(domain, P, message, &R) == self.c {
+            Ok(())
+        } else {
+            Err(MProveSigsError)
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; SCHNORR_BYTES] {
+        let mut bytes = [0u8; SCHNORR_BYTES];
+        bytes[..32].copy_from_slice(self.c.as_bytes());
+        bytes[32..].copy_from_slice(self.s.as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<SchnorrSig, Errors> {
+        if bytes.len() != SCHNORR_BYTES {
+            return Err(MProveSigsError);
+        }
+        let mut reader = bytes;
+        Ok(SchnorrSig {
+            c: read_scalar(&mut reader)?,
+            s: read_scalar(&mut reader)?,
+        })
+    }
+
+    fn challenge<C: Curve>(domain: &[u8], P: &C::Point, message: &[u8], R: &C::Point) -> Scalar {
+        let mut transcript = Transcript::<C>::new(b"Schnorr");
+        transcript.append_message(b"domain", domain);
+        transcript.append_point(b"P", P);
+        transcript.append_message(b"message", message);
+        transcript.append_point(b"R", R);
+        transcript.challenge_scalar(b"c")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::backend::{MoneroEd25519, Ristretto};
+
+    pub fn test_schnorr<C: Curve>(){
+        let mut rng = rand::thread_rng();
+        let x = Scalar::random(&mut rng);
+        let P = C::basepoint() * x;
+
+        let sig = SchnorrSig::sign::<C>(&x, b"test", b"message");
+        assert!(sig.verify::<C>(&P, b"test", b"message").is_ok());
+        assert_eq!(SchnorrSig::from_bytes(&sig.to_bytes()), Ok(sig.clone()));
+
+        // wrong key, domain or message
+        assert_eq!(sig.verify::<C>(&(P + C::basepoint()), b"test", b"message"), Err(MProveSigsError));
+        assert_eq!(sig.verify::<C>(&P, b"other", b"message"), Err(MProveSigsError));
+        assert_eq!(sig.verify::<C>(&P, b"test", b"messagf"), Err(MProveSigsError));
+
+        let mut bytes = sig.to_bytes();
+        bytes[40] ^= 1;
+        assert_eq!(SchnorrSig::from_bytes(&bytes).unwrap().verify::<C>(&P, b"test", b"message"), Err(MProveSigsError));
+        assert_eq!(SchnorrSig::from_bytes(&bytes[1..]), Err(MProveSigsError));
+        let zero = SchnorrSig { c: Scalar::zero(), s: Scalar::zero() };
+        assert_eq!(zero.verify::<C>(&C::identity(), b"test", b"message"), Err(MProveSigsError));
+    }
+
+    #[test]
+    pub fn schnorr(){
+        test_schnorr::<Ristretto>();
+        test_schnorr::<MoneroEd25519>();
+    }
+}
@@ -0,0 +1,409 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Streaming MProve for anonymity sets too large to hold in memory.
+//
+// The ring signatures of position i only involve C_i, P_i and the message,
+// and the message only depends on G, H, n and the context, so the prover
+// can read the anonymity set one output at a time and write each position
+// of the proof as soon as it is done. The proof is written in the layout of
+// MProveProof::to_bytes,
+//     n (u32, little endian) | C_prime_vec | C_res | gamma_vec | sigma_vec
+// where every component of position i has a fixed size, so its offset is
+// known from n and i and the prover seeks to it. The verifier reads the set
+// and seeks through the proof side by side, accumulating C_res, so both
+// sides use memory independent of n, save for the verifier keeping the key
+// image of every position (32 bytes each) so that no output is counted
+// twice. A streamed proof is an ordinary MProveProof once read whole.
+use Errors::{self, MProveError, MProveIoError, MProveWitnessError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::generators::Generators;
+use proofs::mprove::{prove_position, statement_message, verify_position, MProveStatement, MProveWitness};
+use proofs::mprove_sigs::{read_len, read_point, LSAGSig, RingSig, LEN_BYTES, POINT_BYTES};
+use proofs::opening::CResOpening;
+
+use curve25519_dalek::scalar::Scalar;
+
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+// The part of a statement known before its outputs are read
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamHeader<C: Curve = Ristretto> {
+    pub G: C::Point,
+    pub H: C::Point,
+    pub generators: Option<Vec<u8>>,
+    pub n: usize,
+    pub context: Vec<u8>,
+    pub identity: Option<C::Point>,
+}
+
+impl<C: Curve> StreamHeader<C> {
+    pub fn from_statement(stmt: &MProveStatement<C>) -> StreamHeader<C> {
+        StreamHeader {
+            G: stmt.G,
+            H: stmt.H,
+            generators: stmt.generators.clone(),
+            n: stmt.len(),
+            context: stmt.context.clone(),
+            identity: stmt.identity,
+        }
+    }
+
+    // same message as the full statement
+    pub fn message(&self) -> C::Point {
+        statement_message::<C>(
+            &self.G,
+            &self.H,
+            self.generators.as_ref().map(|name| &name[..]),
+            self.n,
+            &self.context,
+            self.identity.as_ref(),
+        )
+    }
+
+    // as MProveStatement::check_generators
+    pub fn check_generators(&self) -> Result<(), Errors> {
+        match self.generators {
+            Some(ref name) => Generators::<C>::from_name(name).check(&self.G, &self.H),
+            None => Ok(()),
+        }
+    }
+}
+
+// Reads an anonymity set stored as consecutive (C_i, P_i) pairs of
+// compressed points, one output at a time
+pub struct OutputReader<C: Curve, R: Read> {
+    reader: R,
+    _curve: PhantomData<C>,
+}
+
+impl<C: Curve, R: Read> OutputReader<C, R> {
+    pub fn new(reader: R) -> OutputReader<C, R> {
+        OutputReader {
+            reader,
+            _curve: PhantomData,
+        }
+    }
+}
+
+impl<C: Curve, R: Read> Iterator for OutputReader<C, R> {
+    type Item = Result<(C::Point, C::Point), Errors>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = [0u8; 2 * POINT_BYTES];
+        match read_full(&mut self.reader, &mut buf) {
+            Ok(0) => None,
+            Ok(len) if len == buf.len() => {
+                let C_i = C::decompress(&buf[..POINT_BYTES]).ok_or(MProveError);
+                let P_i = C::decompress(&buf[POINT_BYTES..]).ok_or(MProveError);
+                Some(C_i.and_then(|C_i| P_i.map(|P_i| (C_i, P_i))))
+            }
+            _ => Some(Err(MProveIoError)),
+        }
+    }
+}
+
+// Offsets of the components of an unclaimed proof of n positions
+struct Layout {
+    n: u64,
+    gamma_len: u64,
+    sigma_len: u64,
+}
+
+impl Layout {
+    fn new<C: Curve>(n: usize) -> Layout {
+        Layout {
+            n: n as u64,
+            gamma_len: RingSig::<C>::encoded_len(2).unwrap() as u64,
+            sigma_len: LSAGSig::<C>::encoded_len(2).unwrap() as u64,
+        }
+    }
+
+    fn C_prime(&self, i: u64) -> u64 {
+        LEN_BYTES as u64 + POINT_BYTES as u64 * i
+    }
+
+    fn C_res(&self) -> u64 {
+        self.C_prime(self.n)
+    }
+
+    fn gamma(&self, i: u64) -> u64 {
+        self.C_res() + POINT_BYTES as u64 + self.gamma_len * i
+    }
+
+    fn sigma(&self, i: u64) -> u64 {
+        self.gamma(self.n) + self.sigma_len * i
+    }
+
+    fn len(&self) -> u64 {
+        self.sigma(self.n)
+    }
+}
+
+fn seek<S: Seek>(stream: &mut S, offset: u64) -> Result<(), Errors> {
+    stream.seek(SeekFrom::Start(offset)).map(|_| ()).map_err(|_| MProveIoError)
+}
+
+// Writes one output in the layout read by OutputReader
+pub fn write_output<C: Curve, W: Write>(writer: &mut W, C_i: &C::Point, P_i: &C::Point) -> Result<(), Errors> {
+    writer.write_all(&C::compress(C_i)).map_err(|_| MProveIoError)?;
+    writer.write_all(&C::compress(P_i)).map_err(|_| MProveIoError)
+}
+
+// Proves the statement whose outputs are read from `outputs`, writing the
+// proof to `writer` from its current start, and returns the opening of
+// C_res. The witness is checked against each output as it goes by.
+pub fn prove_stream<C, I, W>(
+    header: &StreamHeader<C>,
+    outputs: I,
+    witness: &MProveWitness,
+    writer: &mut W,
+) -> Result<CResOpening, Errors>
+where
+    C: Curve,
+    I: IntoIterator<Item = Result<(C::Point, C::Point), Errors>>,
+    W: Write + Seek,
+{
+    profile_span!("prove stream");
+
+    // as MProveStatement::new, an empty set is no statement
+    if header.n == 0 {
+        return Err(MProveError);
+    }
+    header.check_generators()?;
+    let amount = witness.total_amount()?;
+    let G = header.G;
+    let message = header.message();
+    let mut rng = rand::thread_rng();
+    let mut owned = witness.owned().iter().peekable();
+    let layout = Layout::new::<C>(header.n);
+
+    seek(writer, 0)?;
+    writer.write_all(&(header.n as u32).to_le_bytes()).map_err(|_| MProveIoError)?;
+
+    let mut count = 0;
+    let mut C_res = C::identity();
+    let mut blinding = Scalar::zero();
+    for output in outputs {
+        let (C_i, P_i) = output?;
+        let i = count;
+        count += 1;
+        if count > header.n {
+            return Err(MProveError);
+        }
+
+        let z_i = Scalar::random(&mut rng);
+        let x = match owned.next_if(|out| out.index == i) {
+            Some(out) => {
+                if P_i != G * out.x || C_i != G * out.blinding + header.H * Scalar::from(out.amount) {
+                    return Err(MProveWitnessError);
+                }
+                blinding -= out.blinding;
+                Some(out.x)
+            }
+            None => None,
+        };
+        let (C_prime_i, gamma, sigma) = prove_position::<C, _>(message, G, C_i, P_i, z_i, x, &mut rng);
+        C_res = C_res + (C_prime_i - C_i);
+        blinding += z_i;
+
+        let i = i as u64;
+        seek(writer, layout.C_prime(i))?;
+        writer.write_all(&C::compress(&C_prime_i)).map_err(|_| MProveIoError)?;
+        seek(writer, layout.gamma(i))?;
+        writer.write_all(&gamma.to_bytes()).map_err(|_| MProveIoError)?;
+        seek(writer, layout.sigma(i))?;
+        writer.write_all(&sigma.to_bytes()).map_err(|_| MProveIoError)?;
+    }
+
+    // every output read and every owned output found
+    if count != header.n || owned.next().is_some() {
+        return Err(MProveWitnessError);
+    }
+    seek(writer, layout.C_res())?;
+    writer.write_all(&C::compress(&C_res)).map_err(|_| MProveIoError)?;
+    seek(writer, layout.len())?;
+    Ok(CResOpening { amount, blinding })
+}
+
+// Verifies an unclaimed proof read from `reader` against the outputs read
+// from `outputs`, and returns its C_res. A claimed proof is verified whole,
+// with MProveProof::verify_with_identity.
+pub fn verify_stream<C, I, R>(
+    header: &StreamHeader<C>,
+    outputs: I,
+    reader: &mut R,
+) -> Result<C::Point, Errors>
+where
+    C: Curve,
+    I: IntoIterator<Item = Result<(C::Point, C::Point), Errors>>,
+    R: Read + Seek,
+{
+    profile_span!("verify stream");
+
+    if header.n == 0 {
+        return Err(MProveError);
+    }
+    header.check_generators()?;
+    let layout = Layout::new::<C>(header.n);
+    if reader.seek(SeekFrom::End(0)).map_err(|_| MProveIoError)? != layout.len() {
+        return Err(MProveError);
+    }
+    seek(reader, 0)?;
+    if read_len(reader)? != header.n {
+        return Err(MProveError);
+    }
+    let message = header.message();
+
+    let mut count = 0;
+    let mut C_res = C::identity();
+    let mut key_images: HashSet<[u8; 32]> = HashSet::new();
+    for output in outputs {
+        let (C_i, P_i) = output?;
+        count += 1;
+        if count > header.n {
+            return Err(MProveError);
+        }
+
+        let i = (count - 1) as u64;
+        seek(reader, layout.C_prime(i))?;
+        let C_prime_i = read_point::<C, R>(reader)?;
+        seek(reader, layout.gamma(i))?;
+        let gamma = RingSig::<C>::read_from(reader, 2)?;
+        seek(reader, layout.sigma(i))?;
+        let sigma = LSAGSig::<C>::read_from(reader, 2)?;
+        let C_sub_i = C_prime_i - C_i;
+        verify_position(message, C_prime_i, C_sub_i, P_i, &gamma, &sigma)?;
+        if !key_images.insert(C::compress(&sigma.key_image())) {
+            return Err(MProveError);
+        }
+        C_res = C_res + C_sub_i;
+    }
+    if count != header.n {
+        return Err(MProveError);
+    }
+
+    // the stated C_res
+    seek(reader, layout.C_res())?;
+    if read_point::<C, R>(reader)? != C_res {
+        return Err(MProveError);
+    }
+    Ok(C_res)
+}
+
+// fills buf unless the reader ends first, and returns the bytes read
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, Errors> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return Err(MProveIoError),
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::backend::MoneroEd25519;
+    use proofs::fixtures::Fixture;
+    use proofs::mprove::MProveProof;
+    use std::io::Cursor;
+
+    pub fn test_stream<C: Curve>(n: usize, s: usize){
+        let Fixture { stmt, witness } = Fixture::<C>::generate(n, s, 3).unwrap();
+        let header = StreamHeader::from_statement(&stmt);
+
+        // the anonymity set as a byte stream
+        let mut set_bytes: Vec<u8> = Vec::new();
+        for (C_i, P_i) in stmt.C_vec.iter().zip(stmt.P_vec.iter()) {
+            write_output::<C, _>(&mut set_bytes, C_i, P_i).unwrap();
+        }
+
+        let mut cursor = Cursor::new(Vec::new());
+        let opening = prove_stream(&header, OutputReader::<C, _>::new(&set_bytes[..]), &witness, &mut cursor).unwrap();
+        let proof_bytes = cursor.into_inner();
+
+        let C_res = verify_stream(&header, OutputReader::<C, _>::new(&set_bytes[..]), &mut Cursor::new(&proof_bytes)).unwrap();
+        assert_eq!(opening.amount, witness.total_amount().unwrap());
+        assert_eq!(C_res + stmt.H * Scalar::from(opening.amount), stmt.G * opening.blinding);
+
+        // a streamed proof is an ordinary proof
+        let proof = MProveProof::<C>::from_bytes(&proof_bytes).unwrap();
+        assert_eq!(proof.to_bytes(), proof_bytes);
+        assert!(proof.verify(&stmt).is_ok());
+        assert!(opening.verify(&stmt, &proof).is_ok());
+
+        // and one streamed for a claimed statement can be signed
+        let x = Scalar::random(&mut rand::thread_rng());
+        let exchange = C::basepoint() * x;
+        let claimed_stmt = stmt.claimed_by(exchange);
+        let mut cursor = Cursor::new(Vec::new());
+        let claimed_header = StreamHeader::from_statement(&claimed_stmt);
+        prove_stream(&claimed_header, OutputReader::<C, _>::new(&set_bytes[..]), &witness, &mut cursor).unwrap();
+        let mut claimed = MProveProof::<C>::from_bytes(&cursor.into_inner()).unwrap();
+        claimed.sign_identity(&claimed_stmt, &x).unwrap();
+        assert!(claimed.verify_with_identity(&stmt, Some(&exchange)).is_ok());
+        assert!(proof.verify_with_identity(&stmt, Some(&exchange)).is_err());
+        let proved = MProveProof::prove(&stmt, &witness).unwrap();
+        let mut streamed = Cursor::new(Vec::new());
+        streamed.get_mut().extend_from_slice(&proved.to_bytes());
+        assert!(verify_stream(&header, OutputReader::<C, _>::new(&set_bytes[..]), &mut streamed).is_ok());
+
+        // truncated or extended proofs, and a different set
+        let outputs = || OutputReader::<C, _>::new(&set_bytes[..]);
+        let truncated = &proof_bytes[..proof_bytes.len() - 1];
+        assert_eq!(verify_stream(&header, outputs(), &mut Cursor::new(truncated)), Err(MProveError));
+        let mut extended = proof_bytes.clone();
+        extended.push(0);
+        assert_eq!(verify_stream(&header, outputs(), &mut Cursor::new(&extended)), Err(MProveError));
+        let fewer = OutputReader::<C, _>::new(&set_bytes[2 * POINT_BYTES..]);
+        assert!(verify_stream(&header, fewer, &mut Cursor::new(&proof_bytes)).is_err());
+        let mut tampered = proof_bytes.clone();
+        let k = proof_bytes.len() - 8;
+        tampered[k] ^= 1;
+        assert!(verify_stream(&header, outputs(), &mut Cursor::new(&tampered)).is_err());
+
+        // an empty set, with a well-formed proof of nothing
+        let empty_header = StreamHeader { n: 0, ..header.clone() };
+        let mut empty = vec![0u8; 4];
+        empty.extend_from_slice(&C::compress(&C::identity()));
+        let none = std::iter::empty::<Result<(C::Point, C::Point), Errors>>();
+        assert_eq!(verify_stream(&empty_header, none, &mut Cursor::new(&empty)), Err(MProveError));
+        let none = std::iter::empty::<Result<(C::Point, C::Point), Errors>>();
+        let no_witness = MProveWitness::new(Vec::new()).unwrap();
+        assert_eq!(prove_stream(&empty_header, none, &no_witness, &mut Cursor::new(Vec::new())).err(), Some(MProveError));
+
+        // a witness output missing from the set: with every output owned,
+        // one is at n - 1
+        let full = Fixture::<C>::generate(n, n, 3).unwrap();
+        assert_eq!(full.witness.owned().last().unwrap().index, n - 1);
+        let mut full_bytes: Vec<u8> = Vec::new();
+        for (C_i, P_i) in full.stmt.C_vec.iter().zip(full.stmt.P_vec.iter()) {
+            write_output::<C, _>(&mut full_bytes, C_i, P_i).unwrap();
+        }
+        let short_header = StreamHeader { n: n - 1, ..StreamHeader::from_statement(&full.stmt) };
+        let short = OutputReader::<C, _>::new(&full_bytes[..]).take(n - 1);
+        let result = prove_stream(&short_header, short, &full.witness, &mut Cursor::new(Vec::new()));
+        assert_eq!(result.err(), Some(MProveWitnessError));
+    }
+
+    #[test]
+    pub fn sim_stream(){
+        test_stream::<Ristretto>(50, 5);
+        test_stream::<MoneroEd25519>(10, 10);
+    }
+}
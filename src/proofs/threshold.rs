@@ -0,0 +1,596 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Multi-party signing for exchanges whose spend keys are split among
+// custodians.
+//
+// The spend key of a jointly owned output is x = x_1 + ... + x_m, with
+// custodian j holding x_j and publishing X_j = x_j*G, so P = X_1 + ... + X_m
+// and x is never put together. For a signature with P at position pi:
+//     1. each custodian sends its key image share I_j = x_j*H_p(P) with a
+//        proof that log_G(X_j) = log_H_p(P)(I_j), and I = I_1 + ... + I_m,
+//     2. each custodian draws a nonce alpha_j for the signing session (the
+//        message, ring, pi and output) and commits to the session and
+//        L_j = alpha_j*G and R_j = alpha_j*H_p(P),
+//     3. once every commitment is in, the L_j and R_j are revealed, and the
+//        coordinator closes the ring from L = sum L_j and R = sum R_j,
+//     4. each custodian recomputes the challenge c at pi from the closed
+//        ring, L and R, and answers s_j = alpha_j - c*x_j, which the
+//        coordinator checks against L_j and R_j before setting
+//        s_pi = sum s_j.
+// The result is an ordinary RingSig or LSAGSig (a RingSig skips the key
+// image and R_j). The commitments keep any custodian from choosing its nonce
+// after seeing the others. A custodian only answers the challenge of a ring
+// closed for the session its nonce was drawn for, never a challenge handed
+// to it, and a nonce is dropped once it has been answered. Custodians reach
+// the coordinator through the Signer trait; Custodian is the in-process
+// implementation.
+use Errors::{self, MProveThresholdError, MProveWitnessError};
+use proofs::backend::{Curve, Ristretto};
+use proofs::mprove::{prove_position, MProveProof, MProveStatement};
+use proofs::mprove_sigs::{LSAGSig, RingSig};
+use proofs::opening::CResOpening;
+use proofs::transcript::Transcript;
+
+use curve25519_dalek::scalar::Scalar;
+use rand::{CryptoRng, Rng, RngCore};
+
+use std::collections::HashSet;
+
+// Key image share I_j = x_j*H_p(P), with a proof that it uses the same x_j
+// as X_j
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyImageShare<C: Curve = Ristretto> {
+    pub I: C::Point,
+    c: Scalar,
+    s: Scalar,
+}
+
+impl<C: Curve> KeyImageShare<C> {
+    fn prove(x: Scalar, X: &C::Point, base: &C::Point) -> KeyImageShare<C> {
+        let I = *base * x;
+        let k = Scalar::random(&mut rand::thread_rng());
+        let c = share_challenge::<C>(X, base, &I, &(C::basepoint() * k), &(*base * k));
+        KeyImageShare { I, c, s: k - c * x }
+    }
+
+    // checks the share against X_j and the key image base H_p(P)
+    pub fn verify(&self, X: &C::Point, base: &C::Point) -> Result<(), Errors> {
+        let A = C::vartime_double_scalar_mul_basepoint(&self.c, X, &self.s);
+        let B = C::vartime_multiscalar_mul(&[self.s, self.c], &[*base, self.I]);
+        if share_challenge::<C>(X, base, &self.I, &A, &B) == self.c {
+            Ok(())
+        } else {
+            Err(MProveThresholdError)
+        }
+    }
+}
+
+// What a nonce is drawn for: the signers' output k, signed at position
+// index of the ring pk under message, in an LSAG if linkable and a RingSig
+// otherwise
+#[derive(Clone, Debug, PartialEq)]
+pub struct SigningSession<C: Curve = Ristretto> {
+    pub message: C::Point,
+    pub pk: Vec<C::Point>,
+    pub index: usize,
+    pub k: usize,
+    pub linkable: bool,
+}
+
+impl<C: Curve> SigningSession<C> {
+    // H_p(pk[index]), the base of R_j in an LSAG
+    fn base(&self) -> Result<Option<C::Point>, Errors> {
+        let P = self.pk.get(self.index).ok_or(MProveThresholdError)?;
+        Ok(if self.linkable { Some(C::key_image_base(P)) } else { None })
+    }
+}
+
+// Hash of a session and nonce reveal, sent before any reveal
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NonceCommitment(pub [u8; 32]);
+
+// L_j = alpha_j*G, and R_j = alpha_j*H_p(P) for an LSAG
+#[derive(Clone, Debug, PartialEq)]
+pub struct NonceReveal<C: Curve = Ristretto> {
+    pub L: C::Point,
+    pub R: Option<C::Point>,
+}
+
+impl<C: Curve> NonceReveal<C> {
+    pub fn commitment(&self, session: &SigningSession<C>) -> NonceCommitment {
+        let mut transcript = Transcript::<C>::new(b"MProve nonce");
+        transcript.append_point(b"message", &session.message);
+        transcript.append_points(b"pk", &session.pk);
+        transcript.append_u64(b"index", session.index as u64);
+        transcript.append_u64(b"k", session.k as u64);
+        transcript.append_point(b"L", &self.L);
+        if let Some(R) = self.R {
+            transcript.append_point(b"R", &R);
+        }
+        NonceCommitment(transcript.challenge_bytes(b"commitment"))
+    }
+}
+
+// The ring closed by the coordinator from the joint nonce L = sum L_j, and
+// R = sum R_j for an LSAG, with s_index still unset
+#[derive(Clone, Debug)]
+pub enum ClosedRing<C: Curve = Ristretto> {
+    Ring(RingSig<C>, C::Point),
+    LSAG(LSAGSig<C>, C::Point, C::Point),
+}
+
+impl<C: Curve> ClosedRing<C> {
+    // c_index of the session, recomputed from the ring and joint nonce
+    pub fn challenge(&self, session: &SigningSession<C>) -> Result<Scalar, Errors> {
+        let (message, pk, index) = (session.message, &session.pk[..], session.index);
+        match (self, session.linkable) {
+            (ClosedRing::Ring(sig, L), false) => sig.challenge_at(message, pk, index, *L),
+            (ClosedRing::LSAG(sig, L, R), true) => sig.challenge_at(message, pk, index, *L, *R),
+            _ => Err(MProveThresholdError),
+        }
+        .map_err(|_| MProveThresholdError)
+    }
+}
+
+// One custodian, as seen by the coordinator. Outputs are numbered k =
+// 0, 1, ... in the order of the jointly owned outputs.
+pub trait Signer<C: Curve> {
+    // X_j of output k
+    fn public_share(&self, k: usize) -> Result<C::Point, Errors>;
+
+    // I_j of output k, whose address is P
+    fn key_image_share(&self, k: usize, P: &C::Point) -> Result<KeyImageShare<C>, Errors>;
+
+    // draws a fresh nonce for the session, with R_j if it is linkable, and
+    // commits to both
+    fn commit_nonce(&mut self, session: &SigningSession<C>) -> Result<NonceCommitment, Errors>;
+
+    fn reveal_nonce(&mut self) -> Result<NonceReveal<C>, Errors>;
+
+    // s_j = alpha_j - c*x_j for output k, with c recomputed from the ring
+    // closed for the session of the nonce, after which the nonce is dropped
+    fn respond(&mut self, session: &SigningSession<C>, ring: &ClosedRing<C>) -> Result<Scalar, Errors>;
+}
+
+// In-process custodian holding one key share per jointly owned output
+pub struct Custodian<C: Curve = Ristretto> {
+    shares: Vec<Scalar>,
+    nonce: Option<(Scalar, NonceReveal<C>, SigningSession<C>)>,
+}
+
+impl<C: Curve> Custodian<C> {
+    pub fn new(shares: Vec<Scalar>) -> Custodian<C> {
+        Custodian { shares, nonce: None }
+    }
+
+    // fresh shares for `count` outputs
+    pub fn generate<R: Rng + CryptoRng>(count: usize, rng: &mut R) -> Custodian<C> {
+        Custodian::new((0..count).map(|_| Scalar::random(rng)).collect())
+    }
+
+    fn share(&self, k: usize) -> Result<Scalar, Errors> {
+        self.shares.get(k).cloned().ok_or(MProveThresholdError)
+    }
+}
+
+impl<C: Curve> Signer<C> for Custodian<C> {
+    fn public_share(&self, k: usize) -> Result<C::Point, Errors> {
+        Ok(C::basepoint() * self.share(k)?)
+    }
+
+    fn key_image_share(&self, k: usize, P: &C::Point) -> Result<KeyImageShare<C>, Errors> {
+        let x = self.share(k)?;
+        Ok(KeyImageShare::prove(x, &(C::basepoint() * x), &C::key_image_base(P)))
+    }
+
+    fn commit_nonce(&mut self, session: &SigningSession<C>) -> Result<NonceCommitment, Errors> {
+        self.share(session.k)?;
+        let base = session.base()?;
+        let alpha = Scalar::random(&mut rand::thread_rng());
+        let reveal = NonceReveal {
+            L: C::basepoint() * alpha,
+            R: base.map(|base| base * alpha),
+        };
+        let commitment = reveal.commitment(session);
+        self.nonce = Some((alpha, reveal, session.clone()));
+        Ok(commitment)
+    }
+
+    fn reveal_nonce(&mut self) -> Result<NonceReveal<C>, Errors> {
+        self.nonce.as_ref().map(|(_, reveal, _)| reveal.clone()).ok_or(MProveThresholdError)
+    }
+
+    fn respond(&mut self, session: &SigningSession<C>, ring: &ClosedRing<C>) -> Result<Scalar, Errors> {
+        // the nonce is spent whether or not the request is answered
+        let (alpha, _, committed) = self.nonce.take().ok_or(MProveThresholdError)?;
+        if committed != *session {
+            return Err(MProveThresholdError);
+        }
+        let c = ring.challenge(session)?;
+        Ok(alpha - c * self.share(session.k)?)
+    }
+}
+
+// Joint ring signature by the signers for their output k, whose key is
+// pk[index]
+pub fn threshold_RingSig<C: Curve, S: Signer<C>, R: RngCore + CryptoRng>(
+    message: C::Point,
+    pk: &[C::Point],
+    index: usize,
+    signers: &mut [S],
+    k: usize,
+    rng: &mut R,
+) -> Result<RingSig<C>, Errors> {
+
+    let X_vec = public_shares(pk, index, signers, k)?;
+    let session = SigningSession { message, pk: pk.to_vec(), index, k, linkable: false };
+    let reveals = joint_nonce(signers, &session)?;
+    let L_vec: Vec<C::Point> = reveals.iter().map(|reveal| reveal.L).collect();
+
+    let L = C::sum(&L_vec);
+    let (mut sig, c) = RingSig::close_ring(message, pk, index, L, rng);
+    let ring = ClosedRing::Ring(sig.clone(), L);
+    sig.set_response(index, joint_response(signers, &session, &ring, &c, &reveals, &X_vec, None)?);
+    Ok(sig)
+}
+
+// Joint LSAG by the signers for their output k, whose key is pk[index]
+pub fn threshold_LSAG<C: Curve, S: Signer<C>, R: RngCore + CryptoRng>(
+    message: C::Point,
+    pk: &[C::Point],
+    index: usize,
+    signers: &mut [S],
+    k: usize,
+    rng: &mut R,
+) -> Result<LSAGSig<C>, Errors> {
+
+    let X_vec = public_shares(pk, index, signers, k)?;
+
+    // distributed key image
+    let base = C::key_image_base(&pk[index]);
+    let I_vec = signers
+        .iter()
+        .zip(X_vec.iter())
+        .map(|(signer, X)| {
+            let share = signer.key_image_share(k, &pk[index])?;
+            share.verify(X, &base)?;
+            Ok(share.I)
+        })
+        .collect::<Result<Vec<C::Point>, Errors>>()?;
+
+    let session = SigningSession { message, pk: pk.to_vec(), index, k, linkable: true };
+    let reveals = joint_nonce(signers, &session)?;
+    let L_vec: Vec<C::Point> = reveals.iter().map(|reveal| reveal.L).collect();
+    let R_vec = reveals
+        .iter()
+        .map(|reveal| reveal.R.ok_or(MProveThresholdError))
+        .collect::<Result<Vec<C::Point>, Errors>>()?;
+
+    let I = C::sum(&I_vec);
+    let (L, R) = (C::sum(&L_vec), C::sum(&R_vec));
+    let (mut sig, c) = LSAGSig::close_ring(message, pk, index, I, L, R, rng);
+    let ring = ClosedRing::LSAG(sig.clone(), L, R);
+    sig.set_response(index, joint_response(signers, &session, &ring, &c, &reveals, &X_vec, Some((base, &I_vec)))?);
+    Ok(sig)
+}
+
+// An output whose spend key is split among the signers: its position in
+// the anonymity set, amount and blinding factor
+#[derive(Clone)]
+pub struct SharedOutput {
+    pub index: usize,
+    pub amount: u64,
+    pub blinding: Scalar,
+}
+
+impl<C: Curve> MProveProof<C> {
+    // Same as prove_with_opening, for owned outputs whose spend keys are
+    // split among the signers. `owned` is sorted by position, and its k-th
+    // output is the signers' output k. The z_i and all randomness but the
+    // custodians' nonces are drawn from rng.
+    pub fn prove_threshold<S: Signer<C>, R: RngCore + CryptoRng>(
+        stmt: &MProveStatement<C>,
+        owned: &[SharedOutput],
+        signers: &mut [S],
+        rng: &mut R,
+    ) -> Result<(MProveProof<C>, CResOpening), Errors> {
+
+        profile_span!("prove threshold");
+
+        stmt.check_generators()?;
+        for pair in owned.windows(2) {
+            if pair[0].index >= pair[1].index {
+                return Err(MProveWitnessError);
+            }
+        }
+        for out in owned.iter() {
+            if out.index >= stmt.len() || stmt.C_vec[out.index] != stmt.G * out.blinding + stmt.H * Scalar::from(out.amount) {
+                return Err(MProveWitnessError);
+            }
+        }
+        let addresses: HashSet<[u8; 32]> = owned.iter().map(|out| C::compress(&stmt.P_vec[out.index])).collect();
+        if addresses.len() != owned.len() {
+            return Err(MProveWitnessError);
+        }
+        let amount = owned
+            .iter()
+            .try_fold(0u64, |total, out| total.checked_add(out.amount))
+            .ok_or(MProveWitnessError)?;
+
+        let G = stmt.G;
+        let message = stmt.message();
+        let mut owned_iter = owned.iter().enumerate().peekable();
+
+        let mut entries = Vec::with_capacity(stmt.len());
+        let mut terms = Vec::with_capacity(stmt.len());
+        let mut blinding = Scalar::zero();
+        for i in 0..stmt.len() {
+            let (C_i, P_i) = (stmt.C_vec[i], stmt.P_vec[i]);
+            let z_i = Scalar::random(rng);
+            let entry = match owned_iter.next_if(|(_, out)| out.index == i) {
+                // gamma only needs z_i, sigma is signed jointly
+                Some((k, out)) => {
+                    let C_prime_i = G * z_i;
+                    let C_primei_Ci = C_prime_i - C_i;
+                    let gamma = RingSig::gen_RingSig_with_rng(message, &[C_prime_i, C_primei_Ci], z_i, 0, rng);
+                    let sigma = threshold_LSAG(message, &[P_i, C_primei_Ci], 0, signers, k, rng)?;
+                    blinding -= out.blinding;
+                    (C_prime_i, gamma, sigma)
+                }
+                None => prove_position::<C, _>(message, G, C_i, P_i, z_i, None, rng),
+            };
+            terms.push(entry.0 - C_i);
+            entries.push(entry);
+            blinding += z_i;
+        }
+
+        let proof = MProveProof::from_entries(entries, C::sum(&terms));
+        Ok((proof, CResOpening { amount, blinding }))
+    }
+}
+
+// X_j of every signer, which must add up to the key
+fn public_shares<C: Curve, S: Signer<C>>(
+    pk: &[C::Point],
+    index: usize,
+    signers: &[S],
+    k: usize,
+) -> Result<Vec<C::Point>, Errors> {
+    if signers.is_empty() || pk.len() < 2 || index >= pk.len() {
+        return Err(MProveThresholdError);
+    }
+    let X_vec = signers
+        .iter()
+        .map(|signer| signer.public_share(k))
+        .collect::<Result<Vec<C::Point>, Errors>>()?;
+    if C::sum(&X_vec) != pk[index] {
+        return Err(MProveWitnessError);
+    }
+    Ok(X_vec)
+}
+
+// commit round followed by reveal round
+fn joint_nonce<C: Curve, S: Signer<C>>(
+    signers: &mut [S],
+    session: &SigningSession<C>,
+) -> Result<Vec<NonceReveal<C>>, Errors> {
+    let commitments = signers
+        .iter_mut()
+        .map(|signer| signer.commit_nonce(session))
+        .collect::<Result<Vec<NonceCommitment>, Errors>>()?;
+
+    signers
+        .iter_mut()
+        .zip(commitments.iter())
+        .map(|(signer, commitment)| {
+            let reveal = signer.reveal_nonce()?;
+            if reveal.commitment(session) != *commitment || reveal.R.is_some() != session.linkable {
+                return Err(MProveThresholdError);
+            }
+            Ok(reveal)
+        })
+        .collect()
+}
+
+// s_pi = sum of the s_j, each checked against the signer's nonce and shares
+fn joint_response<C: Curve, S: Signer<C>>(
+    signers: &mut [S],
+    session: &SigningSession<C>,
+    ring: &ClosedRing<C>,
+    c: &Scalar,
+    reveals: &[NonceReveal<C>],
+    X_vec: &[C::Point],
+    key_image: Option<(C::Point, &[C::Point])>,
+) -> Result<Scalar, Errors> {
+    let mut s = Scalar::zero();
+    for (j, signer) in signers.iter_mut().enumerate() {
+        let s_j = signer.respond(session, ring)?;
+        if C::vartime_double_scalar_mul_basepoint(c, &X_vec[j], &s_j) != reveals[j].L {
+            return Err(MProveThresholdError);
+        }
+        if let Some((base, I_vec)) = key_image {
+            if Some(C::vartime_multiscalar_mul(&[s_j, *c], &[base, I_vec[j]])) != reveals[j].R {
+                return Err(MProveThresholdError);
+            }
+        }
+        s += s_j;
+    }
+    Ok(s)
+}
+
+fn share_challenge<C: Curve>(
+    X: &C::Point,
+    base: &C::Point,
+    I: &C::Point,
+    A: &C::Point,
+    B: &C::Point,
+) -> Scalar {
+    let mut transcript = Transcript::<C>::new(b"MProve key image share");
+    transcript.append_point(b"X", X);
+    transcript.append_point(b"base", base);
+    transcript.append_point(b"I", I);
+    transcript.append_point(b"A", A);
+    transcript.append_point(b"B", B);
+    transcript.challenge_scalar(b"c")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Errors::MProveError;
+    use proofs::backend::MoneroEd25519;
+    use proofs::fixtures::Fixture;
+
+    // custodian answering with a wrong s_j
+    struct Cheater<C: Curve>(Custodian<C>);
+
+    impl<C: Curve> Signer<C> for Cheater<C> {
+        fn public_share(&self, k: usize) -> Result<C::Point, Errors> {
+            self.0.public_share(k)
+        }
+        fn key_image_share(&self, k: usize, P: &C::Point) -> Result<KeyImageShare<C>, Errors> {
+            self.0.key_image_share(k, P)
+        }
+        fn commit_nonce(&mut self, session: &SigningSession<C>) -> Result<NonceCommitment, Errors> {
+            self.0.commit_nonce(session)
+        }
+        fn reveal_nonce(&mut self) -> Result<NonceReveal<C>, Errors> {
+            self.0.reveal_nonce()
+        }
+        fn respond(&mut self, session: &SigningSession<C>, ring: &ClosedRing<C>) -> Result<Scalar, Errors> {
+            self.0.respond(session, ring).map(|s| s + Scalar::one())
+        }
+    }
+
+    pub fn test_threshold_sigs<C: Curve>(m: usize){
+        let mut rng = rand::thread_rng();
+        let mut custodians: Vec<Custodian<C>> = (0..m).map(|_| Custodian::generate(1, &mut rng)).collect();
+        let x: Scalar = custodians.iter().map(|custodian| custodian.shares[0]).sum();
+        let message = C::random_point(&mut rng);
+
+        let mut pk: Vec<C::Point> = (0..4).map(|_| C::random_point(&mut rng)).collect();
+        pk[2] = C::basepoint() * x;
+
+        let gamma = threshold_RingSig(message, &pk, 2, &mut custodians, 0, &mut rng).unwrap();
+        assert!(gamma.ver_RingSig(message, &pk).is_ok());
+        let sigma = threshold_LSAG(message, &pk, 2, &mut custodians, 0, &mut rng).unwrap();
+        assert!(sigma.ver_LSAG(message, &pk).is_ok());
+        assert_eq!(sigma.key_image(), C::key_image_base(&pk[2]) * x);
+
+        // signing at a position the signers do not own
+        assert_eq!(threshold_LSAG(message, &pk, 1, &mut custodians, 0, &mut rng).err(), Some(MProveWitnessError));
+
+        // a ring of one is refused rather than closed
+        assert_eq!(threshold_RingSig(message, &pk[2..3], 0, &mut custodians, 0, &mut rng).err(), Some(MProveThresholdError));
+        assert_eq!(threshold_LSAG(message, &pk[2..3], 0, &mut custodians, 0, &mut rng).err(), Some(MProveThresholdError));
+
+        // a custodian answering with a wrong s_j is caught
+        let mut signers: Vec<Cheater<C>> = custodians.into_iter().map(Cheater).collect();
+        assert_eq!(threshold_LSAG(message, &pk, 2, &mut signers, 0, &mut rng).err(), Some(MProveThresholdError));
+    }
+
+    pub fn test_custodian<C: Curve>(){
+        let mut rng = rand::thread_rng();
+        let mut custodian = Custodian::<C>::generate(1, &mut rng);
+        let pk = vec![custodian.public_share(0).unwrap(), C::random_point(&mut rng), C::random_point(&mut rng)];
+        let session = SigningSession { message: C::random_point(&mut rng), pk: pk.clone(), index: 0, k: 0, linkable: false };
+        let close = |custodian: &mut Custodian<C>, session: &SigningSession<C>, rng: &mut rand::rngs::ThreadRng| {
+            custodian.commit_nonce(session).unwrap();
+            let L = custodian.reveal_nonce().unwrap().L;
+            let (sig, c) = RingSig::close_ring(session.message, &session.pk, session.index, L, rng);
+            (ClosedRing::Ring(sig, L), c, L)
+        };
+
+        // the answer is to the challenge of the closed ring
+        let (ring, c, L) = close(&mut custodian, &session, &mut rng);
+        assert_eq!(ring.challenge(&session), Ok(c));
+        let s = custodian.respond(&session, &ring).unwrap();
+        assert_eq!(C::vartime_double_scalar_mul_basepoint(&c, &pk[0], &s), L);
+
+        // a nonce is answered at most once
+        assert_eq!(custodian.respond(&session, &ring), Err(MProveThresholdError));
+
+        // no answer for another session, and the nonce is spent
+        let (ring, _, _) = close(&mut custodian, &session, &mut rng);
+        let other = SigningSession { message: C::random_point(&mut rng), ..session.clone() };
+        assert_eq!(custodian.respond(&other, &ring), Err(MProveThresholdError));
+        assert_eq!(custodian.respond(&session, &ring), Err(MProveThresholdError));
+
+        // nor for a ring that does not close from the stated nonce, or of
+        // the wrong kind
+        let (ring, _, _) = close(&mut custodian, &session, &mut rng);
+        let forged = match ring {
+            ClosedRing::Ring(sig, L) => ClosedRing::Ring(sig, L + C::basepoint()),
+            _ => unreachable!(),
+        };
+        assert_eq!(custodian.respond(&session, &forged), Err(MProveThresholdError));
+        let (ring, _, _) = close(&mut custodian, &session, &mut rng);
+        let linkable = SigningSession { linkable: true, ..session.clone() };
+        assert_eq!(ring.challenge(&linkable), Err(MProveThresholdError));
+        assert_eq!(custodian.respond(&session, &ClosedRing::LSAG(LSAGSig::initialize(3), C::basepoint(), C::basepoint())), Err(MProveThresholdError));
+
+        // commitments bind the session
+        custodian.commit_nonce(&session).unwrap();
+        let reveal = custodian.reveal_nonce().unwrap();
+        assert!(reveal.commitment(&session) != reveal.commitment(&SigningSession { k: 1, ..session.clone() }));
+        assert!(reveal.commitment(&session) != reveal.commitment(&SigningSession { index: 1, ..session.clone() }));
+        assert_eq!(custodian.commit_nonce(&SigningSession { k: 1, ..session.clone() }), Err(MProveThresholdError));
+    }
+
+    pub fn test_threshold_mprove<C: Curve>(n: usize, s: usize, m: usize){
+        let mut rng = rand::thread_rng();
+        let Fixture { mut stmt, witness } = Fixture::<C>::generate(n, s, 7).unwrap();
+
+        // the owned addresses are the sums of the custodians' public shares
+        let mut custodians: Vec<Custodian<C>> = (0..m).map(|_| Custodian::generate(s, &mut rng)).collect();
+        let owned: Vec<SharedOutput> = witness
+            .owned()
+            .iter()
+            .enumerate()
+            .map(|(k, out)| {
+                let X_vec: Vec<C::Point> = custodians.iter().map(|custodian| custodian.public_share(k).unwrap()).collect();
+                stmt.P_vec[out.index] = C::sum(&X_vec);
+                SharedOutput { index: out.index, amount: out.amount, blinding: out.blinding }
+            })
+            .collect();
+
+        let (proof, opening) = MProveProof::prove_threshold(&stmt, &owned, &mut custodians, &mut rng).unwrap();
+        assert!(proof.verify(&stmt).is_ok());
+        assert!(opening.verify(&stmt, &proof).is_ok());
+        assert_eq!(opening.amount, witness.total_amount().unwrap());
+
+        // the key images are those of the joint keys
+        let key_images = proof.key_images();
+        for (k, out) in owned.iter().enumerate() {
+            let x: Scalar = custodians.iter().map(|custodian| custodian.shares[k]).sum();
+            assert_eq!(key_images[out.index], C::key_image_base(&stmt.P_vec[out.index]) * x);
+        }
+
+        // generators that are not those of the named set, before any signing
+        let mislabelled = MProveStatement { generators: Some(b"audit".to_vec()), ..stmt.clone() };
+        assert_eq!(MProveProof::prove_threshold(&mislabelled, &owned, &mut custodians, &mut rng).err(), Some(MProveError));
+
+        // a missing custodian
+        custodians.pop();
+        assert_eq!(MProveProof::prove_threshold(&stmt, &owned, &mut custodians, &mut rng).err(), Some(MProveWitnessError));
+    }
+
+    #[test]
+    pub fn sim_threshold(){
+        test_threshold_sigs::<Ristretto>(3);
+        test_threshold_sigs::<MoneroEd25519>(2);
+        test_custodian::<Ristretto>();
+        test_custodian::<MoneroEd25519>();
+        test_threshold_mprove::<Ristretto>(20, 4, 3);
+        test_threshold_mprove::<MoneroEd25519>(6, 2, 2);
+    }
+}
@@ -0,0 +1,120 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Fiat-Shamir transcripts, in the style of Merlin.
+//
+// Every hash in the library goes through a Transcript. A transcript starts
+// with a protocol name, and every append is labelled and length prefixed:
+//     len(label) (u32) | label | len(data) (u64) | data
+// so no two different sequences of appends, and no two protocols, hash the
+// same bytes. A challenge hashes the transcript, with its label, through the
+// curve's H_s or H_p and is then appended itself, so later challenges depend
+// on earlier ones. Transcripts are cheap to clone, which is how a ring
+// signature derives the challenge of every member from a common prefix.
+use proofs::backend::{Curve, Ristretto};
+
+use curve25519_dalek::scalar::Scalar;
+
+use std::marker::PhantomData;
+
+// version of the transcript framing, changed with any change to it
+const PROTOCOL: &[u8] = b"MProve transcript v1";
+
+#[derive(Clone, Debug)]
+pub struct Transcript<C: Curve = Ristretto> {
+    bytes: Vec<u8>,
+    _curve: PhantomData<C>,
+}
+
+impl<C: Curve> Transcript<C> {
+    // Empty transcript of the protocol `domain`
+    pub fn new(domain: &[u8]) -> Transcript<C> {
+        let mut transcript = Transcript {
+            bytes: Vec::new(),
+            _curve: PhantomData,
+        };
+        transcript.append_message(b"protocol", PROTOCOL);
+        transcript.append_message(b"domain", domain);
+        transcript
+    }
+
+    pub fn append_message(&mut self, label: &[u8], message: &[u8]) {
+        self.bytes.extend_from_slice(&(label.len() as u32).to_le_bytes());
+        self.bytes.extend_from_slice(label);
+        self.bytes.extend_from_slice(&(message.len() as u64).to_le_bytes());
+        self.bytes.extend_from_slice(message);
+    }
+
+    pub fn append_point(&mut self, label: &[u8], P: &C::Point) {
+        self.append_message(label, &C::compress(P));
+    }
+
+    pub fn append_points(&mut self, label: &[u8], points: &[C::Point]) {
+        self.append_u64(label, points.len() as u64);
+        for P in points.iter() {
+            self.append_point(label, P);
+        }
+    }
+
+    pub fn append_scalar(&mut self, label: &[u8], s: &Scalar) {
+        self.append_message(label, s.as_bytes());
+    }
+
+    pub fn append_u64(&mut self, label: &[u8], value: u64) {
+        self.append_message(label, &value.to_le_bytes());
+    }
+
+    pub fn challenge_scalar(&mut self, label: &[u8]) -> Scalar {
+        self.append_message(b"challenge", label);
+        let c = C::hash_to_scalar(&self.bytes);
+        self.append_scalar(label, &c);
+        c
+    }
+
+    pub fn challenge_point(&mut self, label: &[u8]) -> C::Point {
+        self.append_message(b"challenge", label);
+        let P = C::hash_to_point(&self.bytes);
+        self.append_point(label, &P);
+        P
+    }
+
+    // 32 challenge bytes, for digests
+    pub fn challenge_bytes(&mut self, label: &[u8]) -> [u8; 32] {
+        self.challenge_scalar(label).to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn transcript_separation(){
+        let challenge = |domain: &[u8], appends: &[(&[u8], &[u8])]| {
+            let mut transcript = Transcript::<Ristretto>::new(domain);
+            for (label, message) in appends.iter() {
+                transcript.append_message(label, message);
+            }
+            transcript.challenge_scalar(b"c")
+        };
+
+        let c = challenge(b"RingSig", &[(b"a", b"bc")]);
+        assert_eq!(c, challenge(b"RingSig", &[(b"a", b"bc")]));
+        assert_ne!(c, challenge(b"LSAG", &[(b"a", b"bc")]));
+        assert_ne!(c, challenge(b"RingSig", &[(b"ab", b"c")]));
+        assert_ne!(c, challenge(b"RingSig", &[(b"a", b"b"), (b"", b"c")]));
+
+        // challenges chain
+        let mut transcript = Transcript::<Ristretto>::new(b"RingSig");
+        let first = transcript.challenge_scalar(b"c");
+        assert_ne!(first, transcript.challenge_scalar(b"c"));
+    }
+}
@@ -0,0 +1,333 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Known-answer test vectors for RingSig, LSAG and MProve.
+//
+// The vectors in test_vectors/ are produced here, one per suite and case.
+// Every input is drawn from a ChaCha20 stream seeded with the vector's seed,
+// and all signing randomness from stream 1 of the same seed, so the vectors
+// can be regenerated bit for bit. Points, scalars and proofs are hex in
+// their wire encoding. Another implementation need not reproduce our
+// randomness: it should accept every published signature and proof, and
+// agree on the key images, C_res and the opening of C_res.
+//
+// Any change to the hashing layout or the wire format changes the vectors,
+// and the test below fails until they are regenerated with
+//     MPROVE_REGENERATE_VECTORS=1 cargo test vectors
+use Errors::{self, MProveError, MProveImportError};
+use proofs::backend::{Curve, MoneroEd25519, Ristretto, RistrettoBlake2b, RistrettoSha512};
+use proofs::export::{decode_point, decode_scalar};
+use proofs::fixtures::Fixture;
+use proofs::generators::Generators;
+use proofs::mprove::{MProveProof, MProveStatement, MProveWitness, OwnedOutput};
+use proofs::mprove_sigs::{LSAGSig, RingSig};
+use proofs::opening::CResOpening;
+
+use curve25519_dalek::scalar::Scalar;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+// suites covered, by their mprove_bin names
+pub const SUITES: &[&str] = &["ristretto", "ristretto-sha512", "ristretto-blake2b", "monero"];
+
+// (seed, ring size) of the signature vectors
+const SIG_CASES: &[(u64, usize)] = &[(1, 2), (2, 5), (3, 11)];
+
+// (seed, n, s) of the MProve vectors
+const MPROVE_CASES: &[(u64, usize, usize)] = &[(1, 1, 1), (2, 3, 0), (3, 4, 2)];
+
+// A RingSig or LSAG vector. key_image is only set for LSAG.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SigVector {
+    pub suite: String,
+    pub seed: u64,
+    pub ring: Vec<String>,
+    pub index: usize,
+    pub secret_key: String,
+    pub message: String,
+    pub signature: String,
+    pub key_image: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OwnedVector {
+    pub index: usize,
+    pub secret_key: String,
+    pub amount: u64,
+    pub blinding: String,
+}
+
+// An MProve vector: the statement, the witness, the proof and what it
+// commits to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MProveVector {
+    pub suite: String,
+    pub seed: u64,
+    pub generators: String,
+    pub context: String,
+    pub G: String,
+    pub H: String,
+    pub C_vec: Vec<String>,
+    pub P_vec: Vec<String>,
+    pub owned: Vec<OwnedVector>,
+    pub proof: String,
+    pub C_res: String,
+    pub key_images: Vec<String>,
+    pub amount: u64,
+    pub blinding: String,
+}
+
+// calls $f::<C>($args) for the curve of the suite named $suite
+macro_rules! by_suite {
+    ($suite:expr, $f:ident, $($arg:expr),*) => {
+        match $suite {
+            "ristretto" => $f::<Ristretto>($($arg),*),
+            "ristretto-sha512" => $f::<RistrettoSha512>($($arg),*),
+            "ristretto-blake2b" => $f::<RistrettoBlake2b>($($arg),*),
+            "monero" => $f::<MoneroEd25519>($($arg),*),
+            _ => Err(MProveImportError),
+        }
+    };
+}
+
+pub fn ring_sig_vectors() -> Result<Vec<SigVector>, Errors> {
+    sig_vectors(false)
+}
+
+pub fn lsag_vectors() -> Result<Vec<SigVector>, Errors> {
+    sig_vectors(true)
+}
+
+pub fn mprove_vectors() -> Result<Vec<MProveVector>, Errors> {
+    let mut vectors = Vec::new();
+    for suite in SUITES.iter() {
+        for &(seed, n, s) in MPROVE_CASES.iter() {
+            vectors.push(by_suite!(*suite, mprove_vector, suite, seed, n, s)?);
+        }
+    }
+    Ok(vectors)
+}
+
+fn sig_vectors(linkable: bool) -> Result<Vec<SigVector>, Errors> {
+    let mut vectors = Vec::new();
+    for suite in SUITES.iter() {
+        for &(seed, n) in SIG_CASES.iter() {
+            vectors.push(by_suite!(*suite, sig_vector, suite, seed, n, linkable)?);
+        }
+    }
+    Ok(vectors)
+}
+
+// the input stream and the signing stream of a seed
+fn rngs(seed: u64) -> (ChaCha20Rng, ChaCha20Rng) {
+    let mut signing = ChaCha20Rng::seed_from_u64(seed);
+    signing.set_stream(1);
+    (ChaCha20Rng::seed_from_u64(seed), signing)
+}
+
+fn sig_vector<C: Curve>(suite: &str, seed: u64, n: usize, linkable: bool) -> Result<SigVector, Errors> {
+    let (mut rng, mut signing) = rngs(seed);
+
+    let mut pk: Vec<C::Point> = (0..n).map(|_| C::random_point(&mut rng)).collect();
+    let index = rng.gen_range(0, n);
+    let x = Scalar::random(&mut rng);
+    pk[index] = C::basepoint() * x;
+    let message = C::random_point(&mut rng);
+
+    let (signature, key_image) = if linkable {
+        let sig = LSAGSig::<C>::gen_LSAG_with_rng(message, &pk, x, index, &mut signing);
+        (sig.to_bytes(), Some(encode_point::<C>(&sig.key_image())))
+    } else {
+        (RingSig::<C>::gen_RingSig_with_rng(message, &pk, x, index, &mut signing).to_bytes(), None)
+    };
+
+    Ok(SigVector {
+        suite: suite.to_string(),
+        seed,
+        ring: pk.iter().map(encode_point::<C>).collect(),
+        index,
+        secret_key: hex::encode(x.as_bytes()),
+        message: encode_point::<C>(&message),
+        signature: hex::encode(signature),
+        key_image,
+    })
+}
+
+fn mprove_vector<C: Curve>(suite: &str, seed: u64, n: usize, s: usize) -> Result<MProveVector, Errors> {
+    let fixture = Fixture::<C>::generate(n, s, seed)?;
+    let (_, mut signing) = rngs(seed);
+    let (proof, opening, _) = MProveProof::prove_with_rng(&fixture.stmt, &fixture.witness, &mut signing)?;
+
+    let stmt = &fixture.stmt;
+    let generators = stmt.generators.as_ref().ok_or(MProveError)?;
+    Ok(MProveVector {
+        suite: suite.to_string(),
+        seed,
+        generators: String::from_utf8(generators.clone()).map_err(|_| MProveError)?,
+        context: hex::encode(&stmt.context),
+        G: encode_point::<C>(&stmt.G),
+        H: encode_point::<C>(&stmt.H),
+        C_vec: stmt.C_vec.iter().map(encode_point::<C>).collect(),
+        P_vec: stmt.P_vec.iter().map(encode_point::<C>).collect(),
+        owned: fixture
+            .witness
+            .owned()
+            .iter()
+            .map(|out| OwnedVector {
+                index: out.index,
+                secret_key: hex::encode(out.x.as_bytes()),
+                amount: out.amount,
+                blinding: hex::encode(out.blinding.as_bytes()),
+            })
+            .collect(),
+        proof: hex::encode(proof.to_bytes()),
+        C_res: encode_point::<C>(&proof.C_res()),
+        key_images: proof.key_images().iter().map(encode_point::<C>).collect(),
+        amount: opening.amount,
+        blinding: hex::encode(opening.blinding.as_bytes()),
+    })
+}
+
+// Checks a published RingSig or LSAG vector: the signature verifies and,
+// for LSAG, links to the key image of the secret key
+pub fn check_sig_vector(vector: &SigVector) -> Result<(), Errors> {
+    by_suite!(vector.suite.as_str(), check_sig, vector)
+}
+
+fn check_sig<C: Curve>(vector: &SigVector) -> Result<(), Errors> {
+    let pk = vector.ring.iter().map(|P| decode_point::<C>(P)).collect::<Result<Vec<_>, _>>()?;
+    let message = decode_point::<C>(&vector.message)?;
+    let x = decode_scalar(&vector.secret_key)?;
+    if pk.get(vector.index) != Some(&(C::basepoint() * x)) {
+        return Err(MProveImportError);
+    }
+    let signature = decode_hex(&vector.signature)?;
+
+    match vector.key_image {
+        Some(ref I) => {
+            let sig = LSAGSig::<C>::from_bytes(&signature)?;
+            sig.ver_LSAG(message, &pk)?;
+            let I = decode_point::<C>(I)?;
+            if sig.key_image() != I || I != C::key_image_base(&pk[vector.index]) * x {
+                return Err(MProveError);
+            }
+            Ok(())
+        }
+        None => RingSig::<C>::from_bytes(&signature)?.ver_RingSig(message, &pk),
+    }
+}
+
+// Checks a published MProve vector: the witness fits the statement, the
+// proof verifies, and C_res, its opening and the key images are those
+// published
+pub fn check_mprove_vector(vector: &MProveVector) -> Result<(), Errors> {
+    by_suite!(vector.suite.as_str(), check_mprove, vector)
+}
+
+fn check_mprove<C: Curve>(vector: &MProveVector) -> Result<(), Errors> {
+    let generators = Generators::<C>::from_name(vector.generators.as_bytes());
+    generators.check(&decode_point::<C>(&vector.G)?, &decode_point::<C>(&vector.H)?)?;
+    let C_vec = vector.C_vec.iter().map(|C_i| decode_point::<C>(C_i)).collect::<Result<Vec<_>, _>>()?;
+    let P_vec = vector.P_vec.iter().map(|P_i| decode_point::<C>(P_i)).collect::<Result<Vec<_>, _>>()?;
+    let stmt = MProveStatement::from_generators(&generators, C_vec, P_vec, &decode_hex(&vector.context)?)?;
+
+    let owned = vector
+        .owned
+        .iter()
+        .map(|out| {
+            Ok(OwnedOutput {
+                index: out.index,
+                x: decode_scalar(&out.secret_key)?,
+                amount: out.amount,
+                blinding: decode_scalar(&out.blinding)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Errors>>()?;
+    MProveWitness::new(owned)?.validate(&stmt)?;
+
+    let proof = MProveProof::<C>::from_bytes(&decode_hex(&vector.proof)?)?;
+    proof.verify(&stmt)?;
+    let key_images = vector.key_images.iter().map(|I| decode_point::<C>(I)).collect::<Result<Vec<_>, _>>()?;
+    if proof.C_res() != decode_point::<C>(&vector.C_res)? || proof.key_images() != key_images {
+        return Err(MProveError);
+    }
+    CResOpening {
+        amount: vector.amount,
+        blinding: decode_scalar(&vector.blinding)?,
+    }
+    .verify(&stmt, &proof)
+}
+
+fn encode_point<C: Curve>(P: &C::Point) -> String {
+    hex::encode(C::compress(P))
+}
+
+fn decode_hex(hex_str: &str) -> Result<Vec<u8>, Errors> {
+    hex::decode(hex_str).map_err(|_| MProveImportError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn path(file: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_vectors").join(file)
+    }
+
+    // Compares the published vectors with freshly generated ones, or
+    // overwrites them if MPROVE_REGENERATE_VECTORS is set
+    fn compare<T: Serialize + DeserializeOwned + PartialEq + ::std::fmt::Debug>(file: &str, generated: &[T]) -> Vec<T> {
+        if env::var_os("MPROVE_REGENERATE_VECTORS").is_some() {
+            let json = serde_json::to_string_pretty(generated).unwrap();
+            fs::write(path(file), json + "\n").unwrap();
+        }
+        let published: Vec<T> = serde_json::from_str(&fs::read_to_string(path(file)).unwrap()).unwrap();
+        assert_eq!(published.len(), generated.len(), "{}", file);
+        for (p, g) in published.iter().zip(generated.iter()) {
+            assert_eq!(p, g, "{} changed", file);
+        }
+        published
+    }
+
+    #[test]
+    pub fn known_answer_vectors(){
+        for vector in compare("ring_sig.json", &ring_sig_vectors().unwrap()).iter() {
+            assert!(vector.key_image.is_none());
+            assert_eq!(check_sig_vector(vector), Ok(()));
+        }
+        for vector in compare("lsag.json", &lsag_vectors().unwrap()).iter() {
+            assert!(vector.key_image.is_some());
+            assert_eq!(check_sig_vector(vector), Ok(()));
+        }
+        for vector in compare("mprove.json", &mprove_vectors().unwrap()).iter() {
+            assert_eq!(check_mprove_vector(vector), Ok(()));
+        }
+
+        // a vector of another suite, or with a flipped bit, is rejected
+        let mut vector = ring_sig_vectors().unwrap().remove(0);
+        vector.suite = "monero".to_string();
+        assert!(check_sig_vector(&vector).is_err());
+        vector.suite = "p256".to_string();
+        assert_eq!(check_sig_vector(&vector), Err(MProveImportError));
+
+        let mut vector = mprove_vectors().unwrap().pop().unwrap();
+        let mut proof = decode_hex(&vector.proof).unwrap();
+        proof[40] ^= 1;
+        vector.proof = hex::encode(proof);
+        assert!(check_mprove_vector(&vector).is_err());
+    }
+}
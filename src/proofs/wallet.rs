@@ -0,0 +1,188 @@
+#![allow(non_snake_case)]
+
+/*
+
+Copyright 2020 by Suyash Bagad, Saravanan Vijayakumaran
+
+This file is part of mProve library
+(<add a link to github>)
+
+*/
+
+// Witnesses built from the exchange's wallet export.
+//
+// The export lists, for every output the exchange owns, its one-time secret
+// key x, its amount and its RingCT mask (blinding factor), with the keys as
+// hex, under the header `secret_key,amount,mask` (see export for the
+// formats). Each output is found in the anonymity set through its one-time
+// address P = x*G.
+use Errors::{self, MProveImportError, MProveMissingOutputError, MProveWitnessError};
+use proofs::backend::Curve;
+use proofs::export::{csv_records, decode_scalar, json_records, load_records, ExportRecord};
+use proofs::mprove::{MProveStatement, MProveWitness, OwnedOutput};
+
+use curve25519_dalek::scalar::Scalar;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+// One exchange-owned output of the wallet export
+#[derive(Clone, Debug, PartialEq)]
+pub struct WalletOutput {
+    pub x: Scalar,
+    pub amount: u64,
+    pub mask: Scalar,
+}
+
+// Raw row of the export, before the keys are decoded
+#[derive(Deserialize)]
+struct WalletRecord {
+    secret_key: String,
+    amount: u64,
+    mask: String,
+}
+
+impl ExportRecord for WalletRecord {
+    const HEADER: &'static [&'static str] = &["secret_key", "amount", "mask"];
+
+    fn from_fields(fields: &[&str]) -> Result<WalletRecord, Errors> {
+        Ok(WalletRecord {
+            secret_key: fields[0].to_string(),
+            amount: fields[1].parse().map_err(|_| MProveImportError)?,
+            mask: fields[2].to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WalletExport {
+    outputs: Vec<WalletOutput>,
+}
+
+impl WalletExport {
+    pub fn new(outputs: Vec<WalletOutput>) -> WalletExport {
+        WalletExport { outputs }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<WalletExport, Errors> {
+        WalletExport::from_records(load_records(path)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<WalletExport, Errors> {
+        WalletExport::from_records(json_records(text)?)
+    }
+
+    pub fn from_csv(text: &str) -> Result<WalletExport, Errors> {
+        WalletExport::from_records(csv_records(text)?)
+    }
+
+    fn from_records(records: Vec<WalletRecord>) -> Result<WalletExport, Errors> {
+        let outputs = records
+            .iter()
+            .map(|record| {
+                Ok(WalletOutput {
+                    x: decode_scalar(&record.secret_key)?,
+                    amount: record.amount,
+                    mask: decode_scalar(&record.mask)?,
+                })
+            })
+            .collect::<Result<Vec<WalletOutput>, Errors>>()?;
+        Ok(WalletExport { outputs })
+    }
+
+    pub fn outputs(&self) -> &[WalletOutput] {
+        &self.outputs
+    }
+
+    // Locates every owned output in the anonymity set of `stmt` and checks
+    // that its commitment opens to (amount, mask). Fails with
+    // MProveMissingOutputError if an output is not in the set, and with
+    // MProveWitnessError if a commitment does not open or an output is
+    // listed twice.
+    pub fn witness<C: Curve>(&self, stmt: &MProveStatement<C>) -> Result<MProveWitness, Errors> {
+
+        let positions: HashMap<[u8; 32], usize> = stmt
+            .P_vec
+            .iter()
+            .enumerate()
+            .map(|(i, P)| (C::compress(P), i))
+            .collect();
+
+        let mut owned = self
+            .outputs
+            .iter()
+            .map(|out| {
+                let P = stmt.G * out.x;
+                let index = *positions.get(&C::compress(&P)).ok_or(MProveMissingOutputError)?;
+                if stmt.C_vec[index] != stmt.G * out.mask + stmt.H * Scalar::from(out.amount) {
+                    return Err(MProveWitnessError);
+                }
+                Ok(OwnedOutput {
+                    index,
+                    x: out.x,
+                    amount: out.amount,
+                    blinding: out.mask,
+                })
+            })
+            .collect::<Result<Vec<OwnedOutput>, Errors>>()?;
+
+        // the witness wants positions sorted, and rejects duplicates
+        owned.sort_by_key(|out| out.index);
+        let witness = MProveWitness::new(owned)?;
+        witness.validate(stmt)?;
+        Ok(witness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proofs::backend::MoneroEd25519;
+    use proofs::fixtures::Fixture;
+    use proofs::mprove::MProveProof;
+
+    #[test]
+    pub fn wallet_witness(){
+        let Fixture { stmt, witness } = Fixture::<MoneroEd25519>::generate(12, 3, 0).unwrap();
+
+        // export the owned outputs in reverse order, as a wallet might
+        let mut csv = String::from("secret_key,amount,mask\n");
+        for out in witness.owned().iter().rev() {
+            csv.push_str(&format!(
+                "{},{},{}\n",
+                hex::encode(out.x.as_bytes()),
+                out.amount,
+                hex::encode(out.blinding.as_bytes())
+            ));
+        }
+        let wallet = WalletExport::from_csv(&csv).unwrap();
+        assert_eq!(wallet.outputs().len(), 3);
+
+        let built = wallet.witness(&stmt).unwrap();
+        let indices: Vec<usize> = built.owned().iter().map(|out| out.index).collect();
+        let expected: Vec<usize> = witness.owned().iter().map(|out| out.index).collect();
+        assert_eq!(indices, expected);
+        let proof = MProveProof::prove(&stmt, &built).unwrap();
+        assert!(proof.verify(&stmt).is_ok());
+
+        // an output outside the anonymity set
+        let mut outputs = wallet.outputs().to_vec();
+        outputs.push(WalletOutput { x: Scalar::from(7u64), amount: 1, mask: Scalar::one() });
+        assert_eq!(WalletExport::new(outputs).witness(&stmt).err(), Some(MProveMissingOutputError));
+
+        // a wrong amount
+        let mut outputs = wallet.outputs().to_vec();
+        outputs[0].amount += 1;
+        assert_eq!(WalletExport::new(outputs).witness(&stmt).err(), Some(MProveWitnessError));
+
+        // the same output twice
+        let mut outputs = wallet.outputs().to_vec();
+        outputs.push(outputs[0].clone());
+        assert_eq!(WalletExport::new(outputs).witness(&stmt).err(), Some(MProveWitnessError));
+
+        // non-canonical scalar
+        let bad = format!("secret_key,amount,mask\n{},1,{}\n", "ff".repeat(32), "00".repeat(32));
+        assert_eq!(WalletExport::from_csv(&bad).err(), Some(MProveImportError));
+        assert!(WalletExport::from_json(r#"[{"secret_key":"00","amount":1,"mask":"00"}]"#).is_err());
+    }
+}